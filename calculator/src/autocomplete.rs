@@ -0,0 +1,209 @@
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+
+use crate::engine::Context;
+use crate::functions::DOCS;
+
+// 弹出框里最多显示的条目数
+const MAX_ITEMS: usize = 8;
+
+struct Candidate {
+    name: String,
+    signature: String,
+    description: String,
+    is_function: bool,
+}
+
+// 输入标识符时弹出的补全列表：内置函数、用户变量和用户函数
+#[derive(Default)]
+pub struct Autocomplete {
+    candidates: Vec<Candidate>,
+    selected: usize,
+    // 正在输入的标识符在输入中的字符范围
+    start: usize,
+    end: usize,
+    prefix: String,
+    dismissed: bool,
+    last_input: String,
+}
+
+impl Autocomplete {
+    // 输入框有焦点时显示；点击候选的瞬间输入框会失去焦点，所以指针在弹出框上时也算
+    fn is_open(&self, ctx: &egui::Context, input_id: egui::Id) -> bool {
+        if self.candidates.is_empty() || self.dismissed {
+            return false;
+        }
+        let popup = ctx.memory(|m| m.area_rect(input_id.with("autocomplete")));
+        let hovered = match (popup, ctx.pointer_hover_pos()) {
+            (Some(rect), Some(pos)) => rect.contains(pos),
+            _ => false,
+        };
+        hovered || ctx.memory(|m| m.has_focus(input_id))
+    }
+
+    // 在输入框处理按键之前调用：上下键选择，Tab / Enter 确认，Esc 关闭
+    pub fn handle_keys(&mut self, ui: &egui::Ui, input_id: egui::Id, input: &mut String) {
+        if !self.is_open(ui.ctx(), input_id) || !ui.memory(|m| m.has_focus(input_id)) {
+            return;
+        }
+        let consume = |key| ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+        if consume(egui::Key::ArrowDown) {
+            self.selected = (self.selected + 1) % self.candidates.len();
+        }
+        if consume(egui::Key::ArrowUp) {
+            self.selected = (self.selected + self.candidates.len() - 1) % self.candidates.len();
+        }
+        if consume(egui::Key::Escape) {
+            self.dismissed = true;
+        }
+        // 已经完整输入了选中的名字时，Enter 留给计算
+        let complete = self.candidates[self.selected].name == self.prefix;
+        if consume(egui::Key::Tab) || (!complete && consume(egui::Key::Enter)) {
+            self.accept(ui.ctx(), input_id, input, self.selected);
+        }
+    }
+
+    // 用第 index 个候选替换正在输入的标识符，函数补上括号并把光标放进括号里
+    fn accept(
+        &mut self,
+        ctx: &egui::Context,
+        input_id: egui::Id,
+        input: &mut String,
+        index: usize,
+    ) {
+        let Some(candidate) = self.candidates.get(index) else {
+            return;
+        };
+        let byte = |chars: usize| {
+            input
+                .char_indices()
+                .nth(chars)
+                .map_or(input.len(), |(i, _)| i)
+        };
+        let (start, end) = (byte(self.start), byte(self.end));
+        let has_paren = input[end..].starts_with('(');
+        let mut insert = candidate.name.clone();
+        if candidate.is_function && !has_paren {
+            insert.push_str("()");
+        }
+        input.replace_range(start..end, &insert);
+        let mut cursor = self.start + candidate.name.chars().count();
+        if candidate.is_function {
+            cursor += 1;
+        }
+
+        let mut state = egui::TextEdit::load_state(ctx, input_id).unwrap_or_default();
+        state
+            .cursor
+            .set_char_range(Some(CCursorRange::one(CCursor::new(cursor))));
+        state.store(ctx, input_id);
+        ctx.memory_mut(|m| m.request_focus(input_id));
+        self.candidates.clear();
+        self.last_input = input.clone();
+        self.dismissed = true;
+    }
+
+    // 输入框处理完之后调用，根据光标前的标识符重新计算候选
+    pub fn update(&mut self, input: &str, cursor: Option<usize>, context: &Context) {
+        if input != self.last_input {
+            self.last_input = input.to_string();
+            self.dismissed = false;
+            self.selected = 0;
+        }
+        // 输入框没有焦点时保留原来的候选，点击弹出框时会出现这种情况
+        let Some(cursor) = cursor else {
+            return;
+        };
+        self.candidates.clear();
+        let before: Vec<char> = input.chars().take(cursor).collect();
+        let start = before
+            .iter()
+            .rposition(|c| !(c.is_alphanumeric() || *c == '_'))
+            .map_or(0, |i| i + 1);
+        // 数字开头的不是标识符，比如 2x 里光标在 2 后面
+        let prefix: String = before[start..].iter().collect();
+        if !prefix.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return;
+        }
+        let end = start
+            + input
+                .chars()
+                .skip(start)
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .count();
+        self.start = start;
+        self.end = end;
+        self.prefix = prefix.clone();
+
+        for (signature, description) in DOCS {
+            let name = signature.split('(').next().unwrap_or(signature);
+            if name.starts_with(&prefix) {
+                self.candidates.push(Candidate {
+                    name: name.to_string(),
+                    signature: signature.to_string(),
+                    description: description.to_string(),
+                    is_function: true,
+                });
+            }
+        }
+        for (name, params) in context.user_functions() {
+            if name.starts_with(&prefix) {
+                self.candidates.push(Candidate {
+                    name: name.to_string(),
+                    signature: format!("{}({})", name, params.join(", ")),
+                    description: "User-defined function".to_string(),
+                    is_function: true,
+                });
+            }
+        }
+        for (name, value) in context.variables() {
+            if name.starts_with(&prefix) {
+                self.candidates.push(Candidate {
+                    name: name.to_string(),
+                    signature: name.to_string(),
+                    description: format!("= {}", value),
+                    is_function: false,
+                });
+            }
+        }
+        // 已经完整输入的名字不再提示
+        if self.candidates.len() == 1 && self.candidates[0].name == prefix {
+            self.candidates.clear();
+        }
+        self.candidates
+            .sort_by(|a, b| a.name.len().cmp(&b.name.len()).then(a.name.cmp(&b.name)));
+        self.candidates.truncate(MAX_ITEMS);
+        self.selected = self.selected.min(self.candidates.len().saturating_sub(1));
+    }
+
+    // 在输入框下方显示候选列表，点击也可以确认
+    pub fn show(
+        &mut self,
+        ui: &egui::Ui,
+        anchor: egui::Rect,
+        input_id: egui::Id,
+        input: &mut String,
+    ) {
+        if !self.is_open(ui.ctx(), input_id) {
+            return;
+        }
+        let mut clicked = None;
+        egui::Area::new(input_id.with("autocomplete"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(anchor.left_bottom())
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, candidate) in self.candidates.iter().enumerate() {
+                        let text = egui::RichText::new(&candidate.signature).monospace();
+                        if ui.selectable_label(i == self.selected, text).clicked() {
+                            clicked = Some(i);
+                        }
+                        ui.label(egui::RichText::new(&candidate.description).weak().small());
+                    }
+                });
+            });
+        if let Some(i) = clicked {
+            self.accept(ui.ctx(), input_id, input, i);
+        }
+    }
+}
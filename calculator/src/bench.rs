@@ -0,0 +1,120 @@
+// calculator --bench：同一组表达式分别按每次重新解析、遍历语法树和字节码求值，比较耗时
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::engine::{self, evaluate, Context, Statement, Value};
+
+// 每个表达式求值的次数，x 在 [-5, 5] 上均匀取点
+const POINTS: usize = 100_000;
+
+const DEFINITIONS: [&str; 2] = ["f(t) = t^2 + 1", "g(t) = f(t) / (1 + f(t))"];
+
+const SUITE: [&str; 8] = [
+    "x^2 + 3*x - 5",
+    "sin(x)^2 + cos(x)^2",
+    "exp(-x^2 / 2) / sqrt(2*pi)",
+    "if x < 0 then -x else x^3",
+    "sqrt(1 + 2^10) * x + ln(1000) / ln(10)",
+    "f(x) + g(x / 2)",
+    "max(x, 0) + abs(x) mod 2",
+    "x > -1 and x < 1 or x == 3",
+];
+
+fn point(i: usize) -> f64 {
+    -5.0 + 10.0 * i as f64 / (POINTS - 1) as f64
+}
+
+fn per_call(elapsed: Duration) -> String {
+    let ns = elapsed.as_secs_f64() * 1e9 / POINTS as f64;
+    if ns >= 1000.0 {
+        format!("{:.2} µs", ns / 1000.0)
+    } else {
+        format!("{:.0} ns", ns)
+    }
+}
+
+pub fn run() -> i32 {
+    let mut ctx = Context::default();
+    for definition in DEFINITIONS {
+        if let Err(err) = evaluate(definition, &mut ctx) {
+            eprintln!("error: {}: {}", definition, err);
+            return 1;
+        }
+    }
+    println!("{} evaluations per expression", POINTS);
+    for definition in DEFINITIONS {
+        println!("  with {}", definition);
+    }
+    println!();
+    println!(
+        "{:<42} {:>10} {:>10} {:>10} {:>6} {:>8}",
+        "expression", "reparse", "tree", "bytecode", "ops", "speedup"
+    );
+    let mut failed = false;
+    for input in SUITE {
+        let expr = match engine::parse(input, &ctx) {
+            Ok(Statement::Expr(expr)) => expr,
+            _ => {
+                eprintln!("error: {}: not an expression", input);
+                return 1;
+            }
+        };
+        let program = match engine::compile(&expr, &ctx, &["x"]) {
+            Ok(program) => program,
+            Err(err) => {
+                eprintln!("error: {}: {}", input, err);
+                return 1;
+            }
+        };
+
+        // 每次都重新解析，相当于原来调用 meval::eval_str
+        let mut scratch = ctx.clone();
+        let start = Instant::now();
+        for i in 0..POINTS {
+            scratch.set_variable("x", Value::Float(point(i)));
+            black_box(evaluate(black_box(input), &mut scratch).ok());
+        }
+        let reparse = start.elapsed();
+
+        let mut scratch = ctx.clone();
+        let start = Instant::now();
+        let mut tree = Vec::with_capacity(POINTS);
+        for i in 0..POINTS {
+            let value =
+                engine::evaluate_at(black_box(&expr), &mut scratch, "x", Value::Float(point(i)));
+            tree.push(value.map_or(f64::NAN, |v| v.to_f64()));
+        }
+        let walked = start.elapsed();
+
+        let start = Instant::now();
+        let mut compiled = Vec::with_capacity(POINTS);
+        for i in 0..POINTS {
+            compiled.push(black_box(&program).run(&[point(i)]));
+        }
+        let bytecode = start.elapsed();
+
+        // 两种求值方式的结果要一致
+        let mismatch = tree.iter().zip(&compiled).position(|(a, b)| {
+            !(a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= 1e-12 * a.abs().max(1.0))
+        });
+        println!(
+            "{:<42} {:>10} {:>10} {:>10} {:>6} {:>7.1}×",
+            input,
+            per_call(reparse),
+            per_call(walked),
+            per_call(bytecode),
+            program.size(),
+            walked.as_secs_f64() / bytecode.as_secs_f64().max(1e-12)
+        );
+        if let Some(i) = mismatch {
+            eprintln!(
+                "  mismatch at x = {}: tree {} vs bytecode {}",
+                point(i),
+                tree[i],
+                compiled[i]
+            );
+            failed = true;
+        }
+    }
+    i32::from(failed)
+}
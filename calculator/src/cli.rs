@@ -0,0 +1,73 @@
+use std::io::BufRead;
+
+use crate::bench;
+use crate::engine::{evaluate, Context};
+use crate::session::Session;
+
+const USAGE: &str = "usage: calculator [--session FILE] [EXPR...] | --bench\n\
+Without arguments the graphical calculator starts.\n\
+With expressions, each one is evaluated in order and its result printed;\n\
+with only --session, lines are read from standard input.\n\
+latex(EXPR) prints an expression as LaTeX without evaluating it.\n\
+--bench times the bytecode compiler against tree-walking evaluation.";
+
+// 命令行模式：可以先载入保存的会话，再逐个计算参数里的表达式，返回退出码
+pub fn run(args: &[String]) -> i32 {
+    let mut context = Context::default();
+    let mut expressions = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return 0;
+            }
+            "--bench" => return bench::run(),
+            "--session" => {
+                let Some(path) = args.next() else {
+                    eprintln!("--session needs a file name\n{}", USAGE);
+                    return 2;
+                };
+                let loaded = std::fs::read_to_string(path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| Session::from_json(&text))
+                    .and_then(|session| session.context());
+                match loaded {
+                    Ok(loaded) => context = loaded,
+                    Err(err) => {
+                        eprintln!("{}: {}", path, err);
+                        return 1;
+                    }
+                }
+            }
+            _ => expressions.push(arg.clone()),
+        }
+    }
+
+    let mut failed = false;
+    let mut run_line = |line: &str| {
+        if line.trim().is_empty() {
+            return;
+        }
+        match evaluate(line, &mut context) {
+            Ok(value) => println!("{}", value),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                failed = true;
+            }
+        }
+    };
+    if expressions.is_empty() {
+        for line in std::io::stdin().lock().lines() {
+            match line {
+                Ok(line) => run_line(&line),
+                Err(_) => break,
+            }
+        }
+    } else {
+        for expression in &expressions {
+            run_line(expression);
+        }
+    }
+    i32::from(failed)
+}
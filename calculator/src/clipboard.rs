@@ -0,0 +1,134 @@
+use eframe::egui;
+
+use crate::engine::{Context, Value, KEYWORDS};
+
+// 按了复制键（Ctrl+C / Cmd+C）时返回是否同时按着 Shift，Shift 表示复制完整精度
+pub fn copy_requested(ctx: &egui::Context) -> Option<bool> {
+    ctx.input(|i| {
+        i.events
+            .iter()
+            .any(|event| matches!(event, egui::Event::Copy))
+            .then_some(i.modifiers.shift)
+    })
+}
+
+// 粘贴的是多行文本时把它从事件里拿走，不让输入框收到，由调用方逐行计算
+pub fn take_multiline_paste(ctx: &egui::Context) -> Option<String> {
+    ctx.input_mut(|i| {
+        let index = i.events.iter().position(
+            |event| matches!(event, egui::Event::Paste(text) if text.trim().contains('\n')),
+        )?;
+        match i.events.remove(index) {
+            egui::Event::Paste(text) => Some(text),
+            _ => None,
+        }
+    })
+}
+
+// 拖进窗口的文本 / CSV 文件：数字读成列表变量，返回一句说明
+pub fn load_dropped(file: &egui::DroppedFile, context: &mut Context) -> Result<String, String> {
+    let (text, name) = match (&file.path, &file.bytes) {
+        (Some(path), _) => (
+            std::fs::read_to_string(path).map_err(|err| err.to_string())?,
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        ),
+        (None, Some(bytes)) => (
+            String::from_utf8_lossy(bytes).into_owned(),
+            file.name.split('.').next().unwrap_or_default().to_string(),
+        ),
+        (None, None) => return Err("dropped file has no contents".to_string()),
+    };
+    let base = identifier(&name).unwrap_or_else(|| "data".to_string());
+    let columns = parse_columns(&text);
+    if columns.is_empty() {
+        return Err(format!("no numbers found in {}", name));
+    }
+    let mut loaded = Vec::new();
+    let single = columns.len() == 1;
+    for (i, (header, values)) in columns.into_iter().enumerate() {
+        // 多列时每列一个变量，优先用表头做名字
+        let variable = match header.as_deref().and_then(identifier) {
+            Some(header) if !single => header,
+            _ if single => base.clone(),
+            _ => format!("{}{}", base, i + 1),
+        };
+        loaded.push(format!("{} ({} numbers)", variable, values.len()));
+        context.set_variable(&variable, Value::List(values));
+    }
+    Ok(format!("loaded {}", loaded.join(", ")))
+}
+
+// 按行拆开，逗号、分号、制表符或空白分列；第一行不全是数字时当作表头，
+// 其余行里不是数字的格子跳过
+pub fn parse_columns(text: &str) -> Vec<(Option<String>, Vec<Value>)> {
+    let rows: Vec<Vec<&str>> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(split_fields)
+        .collect();
+    let Some(first) = rows.first() else {
+        return Vec::new();
+    };
+    let has_header = first.iter().any(|cell| number(cell).is_none());
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut columns: Vec<(Option<String>, Vec<Value>)> = (0..width)
+        .map(|i| {
+            let header = has_header
+                .then(|| first.get(i).map(|cell| cell.to_string()))
+                .flatten();
+            (header, Vec::new())
+        })
+        .collect();
+    let data = if has_header { &rows[1..] } else { &rows[..] };
+    for row in data {
+        for (column, cell) in columns.iter_mut().zip(row) {
+            if let Some(value) = number(cell) {
+                column.1.push(value);
+            }
+        }
+    }
+    columns.retain(|(_, values)| !values.is_empty());
+    columns
+}
+
+fn split_fields(line: &str) -> Vec<&str> {
+    let fields: Vec<&str> = match [',', ';', '\t'].into_iter().find(|&d| line.contains(d)) {
+        Some(delimiter) => line.split(delimiter).collect(),
+        None => line.split_whitespace().collect(),
+    };
+    fields
+        .into_iter()
+        .map(|field| field.trim().trim_matches('"').trim())
+        .collect()
+}
+
+// 普通的数字写法，可以带正负号：12、-3.5、1e6
+fn number(cell: &str) -> Option<Value> {
+    let (negative, digits) = match cell.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, cell.strip_prefix('+').unwrap_or(cell)),
+    };
+    if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return None;
+    }
+    let value = Value::parse_literal(digits)?;
+    if negative {
+        value.neg().ok()
+    } else {
+        Some(value)
+    }
+}
+
+// 把文件名或表头变成合法的变量名，做不到时返回 None
+fn identifier(name: &str) -> Option<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && !KEYWORDS.contains(&name.as_str());
+    valid.then_some(name)
+}
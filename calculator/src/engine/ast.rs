@@ -0,0 +1,272 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+use super::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    // and / or 单独表示，因为要短路求值
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    // 后缀百分号 x%，作为加减法的右操作数时表示左边的百分之 x
+    Percent(Box<Expr>),
+    Call(String, Vec<Expr>),
+    List(Vec<Expr>),
+}
+
+// 一行输入：表达式、变量赋值或函数定义
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Expr(Expr),
+    Assign(String, Expr),
+    Define(String, Vec<String>, Expr),
+}
+
+impl Expr {
+    // 表达式里用到的所有变量名和函数名
+    pub fn collect_names(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Expr::Literal(_) => {}
+            Expr::Variable(name) => {
+                names.insert(name.clone());
+            }
+            Expr::Unary(_, operand) | Expr::Percent(operand) => operand.collect_names(names),
+            Expr::Binary(_, lhs, rhs) | Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.collect_names(names);
+                rhs.collect_names(names);
+            }
+            Expr::If(cond, then, otherwise) => {
+                cond.collect_names(names);
+                then.collect_names(names);
+                otherwise.collect_names(names);
+            }
+            Expr::Call(name, args) => {
+                names.insert(name.clone());
+                for arg in args {
+                    arg.collect_names(names);
+                }
+            }
+            Expr::List(items) => {
+                for item in items {
+                    item.collect_names(names);
+                }
+            }
+        }
+    }
+}
+
+impl Statement {
+    // 这一行定义的变量名或函数名
+    pub fn defines(&self) -> Option<&str> {
+        match self {
+            Statement::Expr(_) => None,
+            Statement::Assign(name, _) | Statement::Define(name, _, _) => Some(name),
+        }
+    }
+
+    // 这一行引用的名字，函数定义里的参数不算
+    pub fn uses(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        match self {
+            Statement::Expr(expr) | Statement::Assign(_, expr) => expr.collect_names(&mut names),
+            Statement::Define(_, params, body) => {
+                body.collect_names(&mut names);
+                for param in params {
+                    names.remove(param);
+                }
+            }
+        }
+        names
+    }
+}
+
+// 打印成可以重新解析的文本，只在必要的地方加括号，隐式乘法写成 *
+impl Expr {
+    // 优先级，和解析器的层次一致，数字越大结合越紧
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Expr::If(..) => 0,
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Unary(UnaryOp::Not, _) => 3,
+            Expr::Binary(op, ..) => match op {
+                BinaryOp::Add | BinaryOp::Sub => 5,
+                BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 6,
+                BinaryOp::Pow => 9,
+                _ => 4,
+            },
+            Expr::Unary(UnaryOp::Neg, _) => 7,
+            Expr::Literal(Value::Int(n)) if n.sign() == num::bigint::Sign::Minus => 7,
+            Expr::Literal(Value::Float(x)) if x.is_sign_negative() => 7,
+            // 逐步求值时代入的多项式、复数、估计值、分解式写出来是一串，当作加法或乘法
+            Expr::Literal(Value::Poly(p))
+                if p.coeffs().iter().filter(|c| **c != 0.0).count() > 1 =>
+            {
+                5
+            }
+            Expr::Literal(Value::Complex(_) | Value::Estimate(..)) => 5,
+            Expr::Literal(Value::Poly(_)) => 6,
+            Expr::Literal(Value::Factors(factors))
+                if factors.len() > 1 || factors.iter().any(|(_, k)| *k > 1) =>
+            {
+                6
+            }
+            Expr::Percent(_) => 8,
+            Expr::Literal(_) | Expr::Variable(_) | Expr::Call(..) | Expr::List(_) => 10,
+        }
+    }
+}
+
+struct Operand<'a>(&'a Expr, u8);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Operand(expr, min) = *self;
+        if expr.precedence() < min {
+            write!(f, "({})", expr)
+        } else {
+            write!(f, "{}", expr)
+        }
+    }
+}
+
+fn join(f: &mut Formatter<'_>, items: &[Expr]) -> std::fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // 浮点数字面量要带小数点，否则会被读成整数
+            Expr::Literal(Value::Float(x)) if x.is_finite() => {
+                let text = x.to_string();
+                if text.contains('.') {
+                    write!(f, "{}", text)
+                } else {
+                    write!(f, "{}.0", text)
+                }
+            }
+            Expr::Literal(value) => write!(f, "{}", value),
+            Expr::Variable(name) => write!(f, "{}", name),
+            Expr::Unary(UnaryOp::Neg, operand) => write!(f, "-{}", Operand(operand, 7)),
+            Expr::Unary(UnaryOp::Not, operand) => write!(f, "not {}", Operand(operand, 3)),
+            Expr::Binary(op, lhs, rhs) => {
+                let (symbol, left, right) = match op {
+                    BinaryOp::Add => (" + ", 5, 6),
+                    BinaryOp::Sub => (" - ", 5, 6),
+                    BinaryOp::Mul => (" * ", 6, 7),
+                    BinaryOp::Div => (" / ", 6, 7),
+                    BinaryOp::Rem => (" mod ", 6, 7),
+                    BinaryOp::Pow => ("^", 10, 7),
+                    BinaryOp::Lt => (" < ", 5, 5),
+                    BinaryOp::Le => (" <= ", 5, 5),
+                    BinaryOp::Gt => (" > ", 5, 5),
+                    BinaryOp::Ge => (" >= ", 5, 5),
+                    BinaryOp::Eq => (" == ", 5, 5),
+                    BinaryOp::Ne => (" != ", 5, 5),
+                };
+                write!(f, "{}{}{}", Operand(lhs, left), symbol, Operand(rhs, right))
+            }
+            Expr::And(lhs, rhs) => write!(f, "{} and {}", Operand(lhs, 2), Operand(rhs, 3)),
+            Expr::Or(lhs, rhs) => write!(f, "{} or {}", Operand(lhs, 1), Operand(rhs, 2)),
+            Expr::If(cond, then, otherwise) => {
+                write!(f, "if {} then {} else {}", cond, then, otherwise)
+            }
+            Expr::Percent(operand) => write!(f, "{}%", Operand(operand, 10)),
+            Expr::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                join(f, args)?;
+                write!(f, ")")
+            }
+            Expr::List(items) => {
+                write!(f, "[")?;
+                join(f, items)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Expr(expr) => write!(f, "{}", expr),
+            Statement::Assign(name, expr) => write!(f, "{} = {}", name, expr),
+            Statement::Define(name, params, body) => {
+                write!(f, "{}({}) = {}", name, params.join(", "), body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{parse, Context};
+
+    // 会话文件按 Display 的文本保存函数定义，重新解析要得到同一个语法树
+    #[test]
+    fn display_round_trips_through_parser() {
+        let ctx = Context::default();
+        for input in [
+            "f(x) = (x^2)%",
+            "(x^2)%",
+            "x^2%",
+            "50% of 80",
+            "a + b% - c%",
+            "-2^2",
+            "(-2)^2",
+            "2^-1",
+            "2^3^2",
+            "(2^3)^2",
+            "1 - (2 - 3)",
+            "8 / (4 / 2)",
+            "-(a + b) * c",
+            "x mod 3 mod 2",
+            "x! + (-x)!",
+            "if x > 0 then 1.5 else -1",
+            "not a and (b or c)",
+            "(a == b) == false",
+            "[1, -2, 3.25]",
+            "g(x, y) = sum(k^2, k, x, y) / 2x",
+        ] {
+            let statement = parse(input, &ctx).unwrap();
+            let printed = statement.to_string();
+            let reparsed = parse(&printed, &ctx)
+                .unwrap_or_else(|e| panic!("{} printed as {}: {}", input, printed, e));
+            assert_eq!(reparsed, statement, "{} printed as {}", input, printed);
+        }
+    }
+}
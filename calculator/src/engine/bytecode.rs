@@ -0,0 +1,562 @@
+// 把表达式编译成实数栈式字节码，给绘图、积分、数值表这种对同一个表达式反复求值的地方用：
+// 不依赖参数的子表达式在编译时按原来的求值器算好，变量放在槽里按下标取
+use super::ast::{BinaryOp, Expr, UnaryOp};
+use super::error::CalcError;
+use super::eval::{eval, Context};
+use super::forms::is_special_form;
+use super::value::Value;
+use crate::functions::{self, FnResult, Function};
+
+// 用户函数最多内联的层数和最多的指令数，递归函数超过后编译失败，由调用方退回按语法树求值
+const MAX_INLINE: usize = 32;
+const MAX_CODE: usize = 10_000;
+
+type RealFn = fn(f64) -> f64;
+
+// 直接用 f64 计算的内置函数，其他纯函数通过 Op::Builtin 调原来的实现
+const UNARY: [(&str, RealFn); 20] = [
+    ("sqrt", f64::sqrt),
+    ("exp", f64::exp),
+    ("ln", f64::ln),
+    ("sin", f64::sin),
+    ("cos", f64::cos),
+    ("tan", f64::tan),
+    ("asin", f64::asin),
+    ("acos", f64::acos),
+    ("atan", f64::atan),
+    ("sinh", f64::sinh),
+    ("cosh", f64::cosh),
+    ("tanh", f64::tanh),
+    ("asinh", f64::asinh),
+    ("acosh", f64::acosh),
+    ("atanh", f64::atanh),
+    ("abs", f64::abs),
+    ("floor", f64::floor),
+    ("ceil", f64::ceil),
+    ("round", f64::round),
+    ("signum", f64::signum),
+];
+
+#[derive(Clone, Copy)]
+enum Op {
+    Const(f64),
+    Load(usize),
+    // 弹出栈顶存进槽里
+    Store(usize),
+    Dup,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Neg,
+    Compare(BinaryOp),
+    Not,
+    // 非零变成 1
+    Truth,
+    Call1(RealFn),
+    Call2(fn(f64, f64) -> f64),
+    Builtin(fn(&[Value]) -> FnResult, usize),
+    Jump(usize),
+    // 弹出条件，为假 / 为真时跳转
+    JumpIfFalse(usize),
+    JumpIfTrue(usize),
+}
+
+// 编译好的程序：前 params 个槽是参数，后面是内联的用户函数的参数
+pub struct Program {
+    code: Vec<Op>,
+    params: usize,
+    slots: usize,
+}
+
+impl Program {
+    // 参数按编译时给的顺序传入；出错或结果不是实数时返回 NaN
+    pub fn run(&self, args: &[f64]) -> f64 {
+        let mut slots = vec![f64::NAN; self.slots];
+        let n = args.len().min(self.params);
+        slots[..n].copy_from_slice(&args[..n]);
+        let mut stack: Vec<f64> = Vec::with_capacity(16);
+        macro_rules! binary {
+            ($f:expr) => {{
+                let b = stack.pop().unwrap_or(f64::NAN);
+                let a = stack.pop().unwrap_or(f64::NAN);
+                stack.push($f(a, b));
+            }};
+        }
+        let mut pc = 0;
+        while let Some(&op) = self.code.get(pc) {
+            pc += 1;
+            match op {
+                Op::Const(x) => stack.push(x),
+                Op::Load(slot) => stack.push(slots[slot]),
+                Op::Store(slot) => slots[slot] = stack.pop().unwrap_or(f64::NAN),
+                Op::Dup => stack.push(stack.last().copied().unwrap_or(f64::NAN)),
+                Op::Add => binary!(|a, b| a + b),
+                Op::Sub => binary!(|a, b| a - b),
+                Op::Mul => binary!(|a, b| a * b),
+                Op::Div => binary!(|a, b| a / b),
+                Op::Rem => binary!(|a, b| a % b),
+                Op::Pow => binary!(f64::powf),
+                Op::Neg => {
+                    let a = stack.pop().unwrap_or(f64::NAN);
+                    stack.push(-a);
+                }
+                Op::Compare(op) => binary!(|a: f64, b: f64| compare(op, a, b)),
+                // 条件是 NaN 时原来的求值器会报错，这里整个结果当作 NaN
+                Op::Not | Op::Truth | Op::JumpIfFalse(_) | Op::JumpIfTrue(_) => {
+                    let a = stack.pop().unwrap_or(f64::NAN);
+                    if a.is_nan() {
+                        return f64::NAN;
+                    }
+                    let truth = a != 0.0;
+                    match op {
+                        Op::Not => stack.push(if truth { 0.0 } else { 1.0 }),
+                        Op::Truth => stack.push(if truth { 1.0 } else { 0.0 }),
+                        Op::JumpIfFalse(target) if !truth => pc = target,
+                        Op::JumpIfTrue(target) if truth => pc = target,
+                        _ => {}
+                    }
+                }
+                Op::Call1(f) => {
+                    let a = stack.pop().unwrap_or(f64::NAN);
+                    stack.push(f(a));
+                }
+                Op::Call2(f) => binary!(f),
+                Op::Builtin(f, argc) => {
+                    let args: Vec<Value> = stack
+                        .drain(stack.len().saturating_sub(argc)..)
+                        .map(Value::Float)
+                        .collect();
+                    stack.push(f(&args).map_or(f64::NAN, |value| real(&value).unwrap_or(f64::NAN)));
+                }
+                Op::Jump(target) => pc = target,
+            }
+        }
+        stack.pop().unwrap_or(f64::NAN)
+    }
+
+    // 指令条数，折叠得越多越短
+    pub fn size(&self) -> usize {
+        self.code.len()
+    }
+}
+
+fn compare(op: BinaryOp, a: f64, b: f64) -> f64 {
+    let result = match op {
+        BinaryOp::Lt => a < b,
+        BinaryOp::Le => a <= b,
+        BinaryOp::Gt => a > b,
+        BinaryOp::Ge => a >= b,
+        BinaryOp::Eq => a == b,
+        _ => a != b,
+    };
+    result as u8 as f64
+}
+
+// 能当作实数用的值
+fn real(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(_)
+        | Value::Float(_)
+        | Value::Factors(_)
+        | Value::Estimate(..)
+        | Value::Bool(_) => Some(value.to_f64()),
+        Value::Complex(z) if z.im == 0.0 => Some(z.re),
+        _ => None,
+    }
+}
+
+// 和 max/min 的实现一样：后面的严格更大（更小）才换
+fn pick_max(a: f64, b: f64) -> f64 {
+    if b > a {
+        b
+    } else {
+        a
+    }
+}
+
+fn pick_min(a: f64, b: f64) -> f64 {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+struct Compiler<'a> {
+    // 折叠常量时用的上下文副本
+    ctx: Context,
+    params: &'a [&'a str],
+    // 内联的用户函数参数对应的槽，内层在后面；和求值器一样是动态作用域
+    scopes: Vec<Vec<(String, usize)>>,
+    slots: usize,
+    code: Vec<Op>,
+}
+
+pub fn compile(expr: &Expr, ctx: &Context, params: &[&str]) -> Result<Program, CalcError> {
+    let mut compiler = Compiler {
+        ctx: ctx.clone(),
+        params,
+        scopes: Vec::new(),
+        slots: params.len(),
+        code: Vec::new(),
+    };
+    compiler.expr(expr)?;
+    Ok(Program {
+        code: compiler.code,
+        params: params.len(),
+        slots: compiler.slots,
+    })
+}
+
+impl Compiler<'_> {
+    // 运行时才知道值的变量所在的槽
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(param, _)| param == name)
+            .map(|(_, slot)| *slot)
+            .or_else(|| self.params.iter().position(|param| *param == name))
+    }
+
+    // 表达式是否依赖运行时的变量，或者调用了随机数这种每次结果不同的函数；
+    // shadowed 是特殊形式或用户函数自己绑定的名字，visiting 是正在检查的用户函数
+    fn depends(&self, expr: &Expr, shadowed: &mut Vec<String>, visiting: &mut Vec<String>) -> bool {
+        match expr {
+            Expr::Literal(_) => false,
+            Expr::Variable(name) => !shadowed.contains(name) && self.resolve(name).is_some(),
+            Expr::Unary(_, x) | Expr::Percent(x) => self.depends(x, shadowed, visiting),
+            Expr::Binary(_, lhs, rhs) | Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                self.depends(lhs, shadowed, visiting) || self.depends(rhs, shadowed, visiting)
+            }
+            Expr::If(cond, then, otherwise) => [cond, then, otherwise]
+                .iter()
+                .any(|x| self.depends(x, shadowed, visiting)),
+            Expr::List(items) => items.iter().any(|x| self.depends(x, shadowed, visiting)),
+            Expr::Call(name, args) if is_special_form(name) => {
+                let bound = match (name.as_str(), args.get(1)) {
+                    ("piecewise" | "latex", _) => None,
+                    (_, Some(Expr::Variable(var))) => Some(var.clone()),
+                    ("poly", None) => Some("x".to_string()),
+                    _ => None,
+                };
+                let n = shadowed.len();
+                shadowed.extend(bound);
+                let result = args.iter().any(|x| self.depends(x, shadowed, visiting));
+                shadowed.truncate(n);
+                result
+            }
+            Expr::Call(name, args) => {
+                if args.iter().any(|x| self.depends(x, shadowed, visiting)) {
+                    return true;
+                }
+                match functions::lookup(name) {
+                    Some(builtin) => matches!(builtin.func, Function::Session(_)),
+                    // 递归调用不用再查一遍，外层已经在查这个函数体了
+                    None if visiting.iter().any(|f| f == name) => false,
+                    None => match self.ctx.user_function(name) {
+                        // 函数体里没被参数遮住的名字也可能指向运行时的变量
+                        Some((params, body)) => {
+                            let n = shadowed.len();
+                            shadowed.extend(params.iter().cloned());
+                            visiting.push(name.clone());
+                            let result = self.depends(body, shadowed, visiting);
+                            visiting.pop();
+                            shadowed.truncate(n);
+                            result
+                        }
+                        None => false,
+                    },
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    // 回填跳转目标为当前位置
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+        match &mut self.code[at] {
+            Op::Jump(t) | Op::JumpIfFalse(t) | Op::JumpIfTrue(t) => *t = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<(), CalcError> {
+        // 常量折叠：不依赖运行时变量的部分直接用求值器算出来
+        if self.code.len() > MAX_CODE {
+            return Err(CalcError::new("cannot compile: expression is too large"));
+        }
+        if !self.depends(expr, &mut Vec::new(), &mut Vec::new()) {
+            let value = eval(expr, &mut self.ctx)?;
+            let Some(x) = real(&value) else {
+                return Err(CalcError::new(format!(
+                    "cannot compile: {} is not a real number",
+                    value
+                )));
+            };
+            self.emit(Op::Const(x));
+            return Ok(());
+        }
+        match expr {
+            Expr::Literal(_) => unreachable!(),
+            Expr::Variable(name) => {
+                let slot = self.resolve(name).unwrap_or_default();
+                self.emit(Op::Load(slot));
+            }
+            Expr::Unary(op, x) => {
+                self.expr(x)?;
+                self.emit(match op {
+                    UnaryOp::Neg => Op::Neg,
+                    UnaryOp::Not => Op::Not,
+                });
+            }
+            // a + b% = a + a*b/100，和求值器的运算顺序一致
+            Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), lhs, rhs)
+                if matches!(**rhs, Expr::Percent(_)) =>
+            {
+                let Expr::Percent(percent) = &**rhs else {
+                    unreachable!()
+                };
+                self.expr(lhs)?;
+                self.emit(Op::Dup);
+                self.expr(percent)?;
+                self.emit(Op::Mul);
+                self.emit(Op::Const(100.0));
+                self.emit(Op::Div);
+                self.emit(if *op == BinaryOp::Add {
+                    Op::Add
+                } else {
+                    Op::Sub
+                });
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                self.emit(match op {
+                    BinaryOp::Add => Op::Add,
+                    BinaryOp::Sub => Op::Sub,
+                    BinaryOp::Mul => Op::Mul,
+                    BinaryOp::Div => Op::Div,
+                    BinaryOp::Rem => Op::Rem,
+                    BinaryOp::Pow => Op::Pow,
+                    op => Op::Compare(*op),
+                });
+            }
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                let and = matches!(expr, Expr::And(..));
+                self.expr(lhs)?;
+                let short = self.emit(if and {
+                    Op::JumpIfFalse(0)
+                } else {
+                    Op::JumpIfTrue(0)
+                });
+                self.expr(rhs)?;
+                self.emit(Op::Truth);
+                let end = self.emit(Op::Jump(0));
+                self.patch(short);
+                self.emit(Op::Const(if and { 0.0 } else { 1.0 }));
+                self.patch(end);
+            }
+            Expr::If(cond, then, otherwise) => {
+                self.expr(cond)?;
+                let skip = self.emit(Op::JumpIfFalse(0));
+                self.expr(then)?;
+                let end = self.emit(Op::Jump(0));
+                self.patch(skip);
+                self.expr(otherwise)?;
+                self.patch(end);
+            }
+            Expr::Percent(x) => {
+                self.expr(x)?;
+                self.emit(Op::Const(100.0));
+                self.emit(Op::Div);
+            }
+            Expr::List(_) => {
+                return Err(CalcError::new("cannot compile: lists are not real numbers"))
+            }
+            Expr::Call(name, args) => self.call(name, args)?,
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> Result<(), CalcError> {
+        if is_special_form(name) {
+            return Err(CalcError::new(format!("cannot compile: {}()", name)));
+        }
+        if let Some(builtin) = functions::lookup(name) {
+            if !builtin.arity.accepts(args.len()) {
+                return Err(CalcError::new(format!(
+                    "{}() takes {} argument(s), got {}",
+                    name,
+                    builtin.arity,
+                    args.len()
+                )));
+            }
+            let unary = UNARY.iter().find(|(n, _)| *n == name);
+            match (name, unary, &builtin.func) {
+                (_, Some(&(_, f)), _) => {
+                    self.expr(&args[0])?;
+                    self.emit(Op::Call1(f));
+                }
+                ("max" | "min", _, _) => {
+                    let pick = if name == "max" { pick_max } else { pick_min };
+                    self.expr(&args[0])?;
+                    for arg in &args[1..] {
+                        self.expr(arg)?;
+                        self.emit(Op::Call2(pick));
+                    }
+                }
+                ("atan2", _, _) => {
+                    self.expr(&args[0])?;
+                    self.expr(&args[1])?;
+                    self.emit(Op::Call2(f64::atan2));
+                }
+                (_, _, Function::Pure(f)) => {
+                    for arg in args {
+                        self.expr(arg)?;
+                    }
+                    self.emit(Op::Builtin(*f, args.len()));
+                }
+                (_, _, Function::Session(_)) => {
+                    return Err(CalcError::new(format!("cannot compile: {}()", name)))
+                }
+            }
+            return Ok(());
+        }
+        // 用户函数：实参存进新的槽，函数体内联
+        let Some((params, body)) = self.ctx.user_function(name) else {
+            return Err(CalcError::new(format!("unknown function '{}'", name)));
+        };
+        if params.len() != args.len() {
+            return Err(CalcError::new(format!(
+                "{}() takes {} argument(s), got {}",
+                name,
+                params.len(),
+                args.len()
+            )));
+        }
+        if self.scopes.len() >= MAX_INLINE {
+            return Err(CalcError::new(format!(
+                "cannot compile: {}() is nested too deeply",
+                name
+            )));
+        }
+        let (params, body) = (params.to_vec(), body.clone());
+        for arg in args {
+            self.expr(arg)?;
+        }
+        let first = self.slots;
+        self.slots += params.len();
+        for slot in (first..self.slots).rev() {
+            self.emit(Op::Store(slot));
+        }
+        self.scopes
+            .push(params.into_iter().zip(first..self.slots).collect());
+        let result = self.expr(&body);
+        self.scopes.pop();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{evaluate, evaluate_at, parse, Statement};
+
+    fn setup() -> Context {
+        let mut ctx = Context::default();
+        for definition in [
+            "f(t) = t^2 + 1",
+            "g(t) = f(t) / (1 + f(t))",
+            "h(t) = if t < 0 then 0 else t + h(t - 1)",
+            "a = 3",
+        ] {
+            evaluate(definition, &mut ctx).unwrap();
+        }
+        ctx
+    }
+
+    fn expression(input: &str, ctx: &Context) -> Expr {
+        match parse(input, ctx).unwrap() {
+            Statement::Expr(expr) => expr,
+            other => panic!("{} is not an expression: {:?}", input, other),
+        }
+    }
+
+    // 表格面板用字节码代替遍历语法树，两者在每个点上的结果必须一致
+    #[test]
+    fn bytecode_matches_tree_evaluation() {
+        let mut ctx = setup();
+        for input in [
+            "x^2 + 3*x - 5",
+            "sin(x)^2 + cos(x)^2",
+            "exp(-x^2 / 2) / sqrt(2*pi)",
+            "if x < 0 then -x else x^3",
+            "sqrt(1 + 2^10) * x + ln(1000) / ln(10)",
+            "f(x) + g(x / 2)",
+            "max(x, 0) + abs(x) mod 2",
+            "x > -1 and x < 1 or x == 3",
+            "not (x >= 2) + (x != 0)",
+            "a*x + 50% of x",
+            "x + 10% - x%",
+            "2x(x - 1) / (x + 0.5)",
+            "sqrt(x) + ln(x)",
+            "x! + floor(x)!",
+            "atan2(x, a) * round(x * 10) / 10",
+        ] {
+            let expr = expression(input, &ctx);
+            let program = compile(&expr, &ctx, &["x"]).unwrap();
+            for i in 0..=40 {
+                let x = -5.0 + i as f64 / 4.0;
+                let tree = evaluate_at(&expr, &mut ctx, "x", Value::Float(x))
+                    .map_or(f64::NAN, |v| v.to_f64());
+                let compiled = program.run(&[x]);
+                let same = tree == compiled
+                    || (tree.is_nan() && compiled.is_nan())
+                    || (tree - compiled).abs() <= 1e-12 * tree.abs().max(1.0);
+                assert!(
+                    same,
+                    "{} at x = {}: tree {} vs bytecode {}",
+                    input, x, tree, compiled
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn constants_are_folded() {
+        let ctx = setup();
+        for (input, size) in [
+            ("2 + 3 * 4", 1),
+            ("f(2) + a", 1),
+            ("sqrt(1 + 2^10) * x", 3),
+            ("x + ln(1000) / ln(10)", 3),
+        ] {
+            let program = compile(&expression(input, &ctx), &ctx, &["x"]).unwrap();
+            assert_eq!(program.size(), size, "{}", input);
+        }
+    }
+
+    // 用户函数内联展开，不留调用
+    #[test]
+    fn user_functions_are_inlined() {
+        let ctx = setup();
+        let program = compile(&expression("g(x)", &ctx), &ctx, &["x"]).unwrap();
+        assert!(program
+            .code
+            .iter()
+            .all(|op| !matches!(op, Op::Builtin(..) | Op::Call1(_) | Op::Call2(_))));
+        assert_eq!(program.run(&[1.0]), 2.0 / 3.0);
+        // 递归展开不完，编译失败，调用方改用语法树求值
+        assert!(compile(&expression("h(x)", &ctx), &ctx, &["x"]).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcError {
+    message: String,
+    // 出错位置在原始输入里的字节范围，只有词法和语法错误才有
+    span: Option<Range<usize>>,
+}
+
+impl CalcError {
+    pub fn new(message: impl Into<String>) -> Self {
+        CalcError {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    pub fn with_span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+}
+
+impl Display for CalcError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+impl From<String> for CalcError {
+    fn from(message: String) -> Self {
+        CalcError::new(message)
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use super::ast::{BinaryOp, Expr, Statement, UnaryOp};
+use super::error::CalcError;
+use super::forms::{is_special_form, special_form};
+use super::value::Value;
+use crate::functions::{self, Function};
+
+// 用户函数调用的最大嵌套深度，防止无限递归把栈撑爆
+const MAX_DEPTH: usize = 256;
+
+// 求值环境：变量、用户定义的函数和会话级的随机数发生器
+#[derive(Clone)]
+pub struct Context {
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, UserFunction>,
+    depth: usize,
+    seed: u64,
+    rng: ChaCha8Rng,
+    implicit_multiplication: bool,
+    // 剩余的求值步数，None 表示不限制
+    steps_left: Option<u64>,
+    // 参数很大时不调用慢的内置函数
+    skip_slow: bool,
+}
+
+// f(x, y) = ... 定义的函数
+#[derive(Clone)]
+struct UserFunction {
+    params: Vec<String>,
+    body: Expr,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        let mut variables = HashMap::new();
+        variables.insert("pi".to_string(), Value::Float(std::f64::consts::PI));
+        variables.insert("π".to_string(), Value::Float(std::f64::consts::PI));
+        variables.insert("e".to_string(), Value::Float(std::f64::consts::E));
+        variables.insert("inf".to_string(), Value::Float(f64::INFINITY));
+        // 默认用随机种子，记下来方便之后复现
+        let seed = rand::random();
+        Context {
+            variables,
+            functions: HashMap::new(),
+            depth: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            implicit_multiplication: true,
+            steps_left: None,
+            skip_slow: false,
+        }
+    }
+}
+
+impl Context {
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 重新设置种子，之后的随机数序列可以复现
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn rng(&mut self) -> &mut ChaCha8Rng {
+        &mut self.rng
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    // 用户定义的函数名和参数列表
+    pub fn user_functions(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.functions
+            .iter()
+            .map(|(name, function)| (name.as_str(), function.params.as_slice()))
+    }
+
+    // 用户函数写回成 f(x) = ... 的源码，按名字排序，保存会话时用
+    pub fn function_definitions(&self) -> Vec<String> {
+        let mut definitions: Vec<(&String, String)> = self
+            .functions
+            .iter()
+            .map(|(name, function)| {
+                let statement =
+                    Statement::Define(name.clone(), function.params.clone(), function.body.clone());
+                (name, statement.to_string())
+            })
+            .collect();
+        definitions.sort();
+        definitions.into_iter().map(|(_, source)| source).collect()
+    }
+
+    // 已定义的变量的值，逐步求值时代入用
+    pub(super) fn variable(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    // 用户函数的参数和函数体，逐步求值时展开用
+    pub(super) fn user_function(&self, name: &str) -> Option<(&[String], &Expr)> {
+        let function = self.functions.get(name)?;
+        Some((&function.params, &function.body))
+    }
+
+    // 关闭后 2x 这样的写法会报错，必须写成 2*x
+    pub fn implicit_multiplication(&self) -> bool {
+        self.implicit_multiplication
+    }
+
+    pub fn set_implicit_multiplication(&mut self, enabled: bool) {
+        self.implicit_multiplication = enabled;
+    }
+
+    // 限制之后最多求值多少个节点，用于输入时的实时预览
+    pub fn limit_steps(&mut self, steps: u64) {
+        self.steps_left = Some(steps);
+    }
+
+    // 实时预览用：factor、nCr、is_prime 这些估计要算很久时直接报错，不在界面线程上算
+    pub fn skip_slow_functions(&mut self) {
+        self.skip_slow = true;
+    }
+
+    // 临时绑定一个变量，执行完 f 后恢复原值
+    pub fn with_binding<T>(
+        &mut self,
+        name: &str,
+        value: Value,
+        f: impl FnOnce(&mut Context) -> T,
+    ) -> T {
+        let previous = self.variables.insert(name.to_string(), value);
+        let result = f(self);
+        match previous {
+            Some(old) => self.variables.insert(name.to_string(), old),
+            None => self.variables.remove(name),
+        };
+        result
+    }
+
+    // 同时绑定多个变量，用于调用用户函数
+    fn with_bindings<T>(
+        &mut self,
+        bindings: Vec<(String, Value)>,
+        f: impl FnOnce(&mut Context) -> T,
+    ) -> T {
+        let previous: Vec<_> = bindings
+            .into_iter()
+            .map(|(name, value)| {
+                let old = self.variables.insert(name.clone(), value);
+                (name, old)
+            })
+            .collect();
+        let result = f(self);
+        for (name, old) in previous.into_iter().rev() {
+            match old {
+                Some(old) => self.variables.insert(name, old),
+                None => self.variables.remove(&name),
+            };
+        }
+        result
+    }
+}
+
+// 执行一行输入：赋值返回被赋的值，函数定义返回一句提示
+pub fn eval_statement(statement: &Statement, ctx: &mut Context) -> Result<Value, CalcError> {
+    match statement {
+        Statement::Expr(expr) => eval(expr, ctx),
+        Statement::Assign(name, expr) => {
+            let value = eval(expr, ctx)?;
+            ctx.variables.insert(name.clone(), value.clone());
+            Ok(value)
+        }
+        Statement::Define(name, params, body) => {
+            if functions::lookup(name).is_some() || is_special_form(name) {
+                return Err(CalcError::new(format!(
+                    "cannot redefine built-in function '{}'",
+                    name
+                )));
+            }
+            ctx.functions.insert(
+                name.clone(),
+                UserFunction {
+                    params: params.clone(),
+                    body: body.clone(),
+                },
+            );
+            Ok(Value::Text(format!(
+                "defined {}({})",
+                name,
+                params.join(", ")
+            )))
+        }
+    }
+}
+
+pub fn eval(expr: &Expr, ctx: &mut Context) -> Result<Value, CalcError> {
+    if let Some(steps) = &mut ctx.steps_left {
+        if *steps == 0 {
+            return Err(CalcError::new("step budget exceeded"));
+        }
+        *steps -= 1;
+    }
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Variable(name) => ctx
+            .variables
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CalcError::new(format!("unknown variable '{}'", name))),
+        Expr::Unary(UnaryOp::Neg, operand) => Ok(eval(operand, ctx)?.neg()?),
+        Expr::Unary(UnaryOp::Not, operand) => Ok(Value::Bool(!condition(operand, ctx)?)),
+        // and / or 短路求值
+        Expr::And(lhs, rhs) => Ok(Value::Bool(condition(lhs, ctx)? && condition(rhs, ctx)?)),
+        Expr::Or(lhs, rhs) => Ok(Value::Bool(condition(lhs, ctx)? || condition(rhs, ctx)?)),
+        Expr::If(cond, then, otherwise) => {
+            if condition(cond, ctx)? {
+                eval(then, ctx)
+            } else {
+                eval(otherwise, ctx)
+            }
+        }
+        Expr::List(items) => Ok(Value::List(
+            items
+                .iter()
+                .map(|item| eval(item, ctx))
+                .collect::<Result<_, _>>()?,
+        )),
+        Expr::Percent(operand) => Ok(eval(operand, ctx)?.div(&hundred())?),
+        Expr::Binary(op, lhs, rhs) => {
+            if let (BinaryOp::Add | BinaryOp::Sub, Expr::Percent(percent)) = (op, &**rhs) {
+                return add_percent(*op, lhs, percent, ctx);
+            }
+            let a = eval(lhs, ctx)?;
+            let b = eval(rhs, ctx)?;
+            let result = match op {
+                BinaryOp::Add => a.add(&b),
+                BinaryOp::Sub => a.sub(&b),
+                BinaryOp::Mul => a.mul(&b),
+                BinaryOp::Div => a.div(&b),
+                BinaryOp::Rem => a.rem(&b),
+                BinaryOp::Pow => a.pow(&b),
+                op => a.compare(*op, &b),
+            };
+            Ok(result?)
+        }
+        Expr::Call(name, args) => {
+            if let Some(result) = special_form(name, args, ctx) {
+                return result;
+            }
+            let Some(builtin) = functions::lookup(name) else {
+                return call_user_function(name, args, ctx);
+            };
+            if !builtin.arity.accepts(args.len()) {
+                return Err(CalcError::new(format!(
+                    "{}() takes {} argument(s), got {}",
+                    name,
+                    builtin.arity,
+                    args.len()
+                )));
+            }
+            let values = args
+                .iter()
+                .map(|arg| eval(arg, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            if ctx.skip_slow && builtin.slow.is_some_and(|too_slow| too_slow(&values)) {
+                return Err(CalcError::new(format!("{}() is too slow to preview", name)));
+            }
+            let result = match builtin.func {
+                Function::Pure(func) => func(&values),
+                Function::Session(func) => func(ctx, &values),
+            };
+            result.map_err(|message| CalcError::new(format!("{}: {}", name, message)))
+        }
+    }
+}
+
+fn hundred() -> Value {
+    Value::Int(100.into())
+}
+
+// 200 + 10% = 220，200 - 10% = 180，和桌面计算器一样
+fn add_percent(
+    op: BinaryOp,
+    lhs: &Expr,
+    percent: &Expr,
+    ctx: &mut Context,
+) -> Result<Value, CalcError> {
+    let base = eval(lhs, ctx)?;
+    let delta = base.mul(&eval(percent, ctx)?)?.div(&hundred())?;
+    let result = if op == BinaryOp::Add {
+        base.add(&delta)
+    } else {
+        base.sub(&delta)
+    };
+    Ok(result?)
+}
+
+pub fn condition(expr: &Expr, ctx: &mut Context) -> Result<bool, CalcError> {
+    Ok(eval(expr, ctx)?.truthy()?)
+}
+
+fn call_user_function(name: &str, args: &[Expr], ctx: &mut Context) -> Result<Value, CalcError> {
+    let Some(function) = ctx.functions.get(name) else {
+        return Err(CalcError::new(format!("unknown function '{}'", name)));
+    };
+    if function.params.len() != args.len() {
+        return Err(CalcError::new(format!(
+            "{}() takes {} argument(s), got {}",
+            name,
+            function.params.len(),
+            args.len()
+        )));
+    }
+    if ctx.depth >= MAX_DEPTH {
+        return Err(CalcError::new(format!(
+            "{}: maximum recursion depth exceeded",
+            name
+        )));
+    }
+    let params = function.params.clone();
+    let body = function.body.clone();
+    let values = args
+        .iter()
+        .map(|arg| eval(arg, ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    ctx.depth += 1;
+    let bindings = params.into_iter().zip(values).collect();
+    let result = ctx.with_bindings(bindings, |ctx| eval(&body, ctx));
+    ctx.depth -= 1;
+    result
+}
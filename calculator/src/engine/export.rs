@@ -0,0 +1,485 @@
+// 把语法树导出成 LaTeX 或 MathML：括号和优先级在 render 里统一处理，两种记法只管具体写法
+use super::ast::{BinaryOp, Expr, Statement, UnaryOp};
+use super::value::Value;
+
+// 会写成希腊字母的变量名
+const GREEK: [(&str, &str); 18] = [
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("phi", "φ"),
+    ("omega", "ω"),
+];
+
+// LaTeX 里有现成命令的函数，其他的用 \operatorname
+const LATEX_FUNCTIONS: [&str; 14] = [
+    "sin", "cos", "tan", "sinh", "cosh", "tanh", "ln", "log", "exp", "min", "max", "gcd", "det",
+    "arg",
+];
+
+trait Notation {
+    fn number(&self, text: &str) -> String;
+    fn identifier(&self, name: &str) -> String;
+    fn text(&self, text: &str) -> String;
+    // 中缀或前缀运算符，symbol 是输入里的写法，比如 "<=" 和 "and"，负号是 "neg"
+    fn operator(&self, symbol: &str) -> String;
+    fn row(&self, parts: Vec<String>) -> String;
+    fn fenced(&self, inner: String, open: &str, close: &str) -> String;
+    fn fraction(&self, numerator: String, denominator: String) -> String;
+    fn power(&self, base: String, exponent: String) -> String;
+    fn root(&self, inner: String) -> String;
+    fn function(&self, name: &str) -> String;
+    fn matrix(&self, rows: Vec<Vec<String>>) -> String;
+    // (值, 条件) 的列表，条件为 None 的是默认值
+    fn cases(&self, cases: Vec<(String, Option<String>)>) -> String;
+    // ∑ ∏ ∫ 这样带上下限的运算符
+    fn big(&self, symbol: &str, lower: String, upper: String) -> String;
+}
+
+struct Latex;
+struct MathMl;
+
+pub fn latex(statement: &Statement) -> String {
+    statement_with(&Latex, statement)
+}
+
+pub fn latex_expr(expr: &Expr) -> String {
+    render(&Latex, expr)
+}
+
+pub fn mathml(statement: &Statement) -> String {
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">{}</math>",
+        statement_with(&MathMl, statement)
+    )
+}
+
+fn statement_with(n: &impl Notation, statement: &Statement) -> String {
+    match statement {
+        Statement::Expr(expr) => render(n, expr),
+        Statement::Assign(name, expr) => {
+            n.row(vec![n.identifier(name), n.operator("="), render(n, expr)])
+        }
+        Statement::Define(name, params, body) => {
+            let params = params
+                .iter()
+                .enumerate()
+                .flat_map(|(i, p)| {
+                    let comma = (i > 0).then(|| n.operator(","));
+                    comma.into_iter().chain([n.identifier(p)])
+                })
+                .collect();
+            n.row(vec![
+                n.function(name),
+                n.fenced(n.row(params), "(", ")"),
+                n.operator("="),
+                render(n, body),
+            ])
+        }
+    }
+}
+
+// 分数线自己就能分组，当作最高优先级
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary(BinaryOp::Div, ..) => 10,
+        Expr::Call(name, args) if name == "factorial" && args.len() == 1 => 8,
+        // ∑ 和 ∫ 一直管到右边，和乘法一样
+        Expr::Call(name, args)
+            if matches!(name.as_str(), "sum" | "prod" | "integrate") && args.len() == 4 =>
+        {
+            6
+        }
+        _ => expr.precedence(),
+    }
+}
+
+fn operand(n: &impl Notation, expr: &Expr, min: u8) -> String {
+    if precedence(expr) < min {
+        n.fenced(render(n, expr), "(", ")")
+    } else {
+        render(n, expr)
+    }
+}
+
+fn items(n: &impl Notation, items: &[Expr]) -> String {
+    let mut parts = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            parts.push(n.operator(","));
+        }
+        parts.push(render(n, item));
+    }
+    n.row(parts)
+}
+
+fn render(n: &impl Notation, expr: &Expr) -> String {
+    match expr {
+        Expr::Literal(value) => literal(n, value),
+        Expr::Variable(name) => n.identifier(name),
+        Expr::Unary(UnaryOp::Neg, x) => n.row(vec![n.operator("neg"), operand(n, x, 7)]),
+        Expr::Unary(UnaryOp::Not, x) => n.row(vec![n.operator("not"), operand(n, x, 3)]),
+        Expr::Binary(BinaryOp::Div, lhs, rhs) => n.fraction(render(n, lhs), render(n, rhs)),
+        Expr::Binary(BinaryOp::Pow, base, exponent) => {
+            // 指数已经单独放在上标里，不用括号；底数是分数、乘方或负数时要加
+            let simple =
+                precedence(base) == 10 && !matches!(**base, Expr::Binary(BinaryOp::Div, ..));
+            let base = if simple {
+                render(n, base)
+            } else {
+                n.fenced(render(n, base), "(", ")")
+            };
+            n.power(base, render(n, exponent))
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let (symbol, left, right) = match op {
+                BinaryOp::Add => ("+", 5, 6),
+                BinaryOp::Sub => ("-", 5, 6),
+                BinaryOp::Mul => ("*", 6, 7),
+                BinaryOp::Rem => ("mod", 6, 7),
+                BinaryOp::Lt => ("<", 5, 5),
+                BinaryOp::Le => ("<=", 5, 5),
+                BinaryOp::Gt => (">", 5, 5),
+                BinaryOp::Ge => (">=", 5, 5),
+                BinaryOp::Eq => ("==", 5, 5),
+                BinaryOp::Ne => ("!=", 5, 5),
+                BinaryOp::Div | BinaryOp::Pow => unreachable!(),
+            };
+            // a - (-3) 比 a - -3 好读，右边是负数时加括号
+            let negative = rhs.precedence() == 7
+                && matches!(
+                    op,
+                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Rem
+                );
+            let rhs = if negative {
+                n.fenced(render(n, rhs), "(", ")")
+            } else {
+                operand(n, rhs, right)
+            };
+            n.row(vec![operand(n, lhs, left), n.operator(symbol), rhs])
+        }
+        Expr::And(lhs, rhs) => n.row(vec![
+            operand(n, lhs, 2),
+            n.operator("and"),
+            operand(n, rhs, 3),
+        ]),
+        Expr::Or(lhs, rhs) => n.row(vec![
+            operand(n, lhs, 1),
+            n.operator("or"),
+            operand(n, rhs, 2),
+        ]),
+        Expr::If(cond, then, otherwise) => n.cases(vec![
+            (render(n, then), Some(render(n, cond))),
+            (render(n, otherwise), None),
+        ]),
+        Expr::Percent(x) => n.row(vec![operand(n, x, 9), n.operator("%")]),
+        Expr::Call(name, args) => call(n, name, args),
+        Expr::List(list) => {
+            let width = match list.first() {
+                Some(Expr::List(row)) if !row.is_empty() => row.len(),
+                _ => 0,
+            };
+            let is_matrix = width > 0
+                && list
+                    .iter()
+                    .all(|item| matches!(item, Expr::List(row) if row.len() == width));
+            if is_matrix {
+                let rows = list
+                    .iter()
+                    .map(|item| match item {
+                        Expr::List(row) => row.iter().map(|cell| render(n, cell)).collect(),
+                        _ => Vec::new(),
+                    })
+                    .collect();
+                n.matrix(rows)
+            } else {
+                n.fenced(items(n, list), "[", "]")
+            }
+        }
+    }
+}
+
+fn call(n: &impl Notation, name: &str, args: &[Expr]) -> String {
+    match (name, args) {
+        ("sqrt", [x]) => n.root(render(n, x)),
+        ("abs", [x]) => n.fenced(render(n, x), "|", "|"),
+        ("factorial", [x]) => n.row(vec![operand(n, x, 10), n.operator("!")]),
+        ("sum" | "prod", [body, Expr::Variable(k), from, to]) => {
+            let symbol = if name == "sum" { "∑" } else { "∏" };
+            let lower = n.row(vec![n.identifier(k), n.operator("="), render(n, from)]);
+            n.row(vec![
+                n.big(symbol, lower, render(n, to)),
+                operand(n, body, 6),
+            ])
+        }
+        ("integrate", [body, Expr::Variable(x), from, to]) => n.row(vec![
+            n.big("∫", render(n, from), render(n, to)),
+            operand(n, body, 6),
+            n.operator("d"),
+            n.identifier(x),
+        ]),
+        ("piecewise", _) => n.cases(
+            args.chunks(2)
+                .map(|pair| match pair {
+                    [cond, value] => (render(n, value), Some(render(n, cond))),
+                    [default] => (render(n, default), None),
+                    _ => unreachable!(),
+                })
+                .collect(),
+        ),
+        _ => n.row(vec![n.function(name), n.fenced(items(n, args), "(", ")")]),
+    }
+}
+
+fn literal(n: &impl Notation, value: &Value) -> String {
+    match value {
+        Value::Int(_) | Value::Float(_) => {
+            let text = value.to_string();
+            match text.strip_prefix('-') {
+                Some(digits) => n.row(vec![n.operator("neg"), n.number(digits)]),
+                None => n.number(&text),
+            }
+        }
+        Value::Bool(b) => n.text(&b.to_string()),
+        other => n.text(&other.to_string()),
+    }
+}
+
+fn greek(name: &str) -> Option<(&'static str, &'static str)> {
+    GREEK
+        .iter()
+        .find(|(word, letter)| *word == name || *letter == name)
+        .copied()
+}
+
+// 转义 LaTeX 里有特殊含义的字符，单元格引用 $1 里的 $ 也在其中
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '_' | '$' | '#' | '%' | '&') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl Notation for Latex {
+    fn number(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn identifier(&self, name: &str) -> String {
+        match (name, greek(name)) {
+            ("inf", _) => "\\infty".to_string(),
+            (_, Some((word, _))) => format!("\\{}", word),
+            _ if name.chars().count() == 1 => name.to_string(),
+            _ => format!("\\mathrm{{{}}}", escape_latex(name)),
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!("\\text{{{}}}", escape_latex(text))
+    }
+
+    fn operator(&self, symbol: &str) -> String {
+        match symbol {
+            "*" => " \\cdot ",
+            "mod" => " \\bmod ",
+            "<=" => " \\le ",
+            ">=" => " \\ge ",
+            "==" => " = ",
+            "!=" => " \\ne ",
+            "and" => " \\land ",
+            "or" => " \\lor ",
+            "not" => "\\lnot ",
+            "%" => "\\%",
+            "," => ", ",
+            "d" => " \\, d",
+            "neg" => "-",
+            "!" => symbol,
+            _ => return format!(" {} ", symbol),
+        }
+        .to_string()
+    }
+
+    fn row(&self, parts: Vec<String>) -> String {
+        let text = parts.concat();
+        // 开头的负号紧贴着后面，前后多余的空格去掉
+        text.trim().to_string()
+    }
+
+    fn fenced(&self, inner: String, open: &str, close: &str) -> String {
+        format!("\\left{} {} \\right{}", open, inner, close)
+    }
+
+    fn fraction(&self, numerator: String, denominator: String) -> String {
+        format!("\\frac{{{}}}{{{}}}", numerator, denominator)
+    }
+
+    fn power(&self, base: String, exponent: String) -> String {
+        format!("{}^{{{}}}", base, exponent)
+    }
+
+    fn root(&self, inner: String) -> String {
+        format!("\\sqrt{{{}}}", inner)
+    }
+
+    fn function(&self, name: &str) -> String {
+        if LATEX_FUNCTIONS.contains(&name) {
+            format!("\\{}", name)
+        } else if name.chars().count() == 1 {
+            name.to_string()
+        } else {
+            format!("\\operatorname{{{}}}", name.replace('_', "\\_"))
+        }
+    }
+
+    fn matrix(&self, rows: Vec<Vec<String>>) -> String {
+        let rows: Vec<String> = rows.iter().map(|row| row.join(" & ")).collect();
+        format!(
+            "\\begin{{bmatrix}} {} \\end{{bmatrix}}",
+            rows.join(" \\\\ ")
+        )
+    }
+
+    fn cases(&self, cases: Vec<(String, Option<String>)>) -> String {
+        let lines: Vec<String> = cases
+            .into_iter()
+            .map(|(value, condition)| match condition {
+                Some(condition) => format!("{} & \\text{{if }} {}", value, condition),
+                None => format!("{} & \\text{{otherwise}}", value),
+            })
+            .collect();
+        format!("\\begin{{cases}} {} \\end{{cases}}", lines.join(" \\\\ "))
+    }
+
+    fn big(&self, symbol: &str, lower: String, upper: String) -> String {
+        let command = match symbol {
+            "∑" => "\\sum",
+            "∏" => "\\prod",
+            _ => "\\int",
+        };
+        format!("{}_{{{}}}^{{{}}} ", command, lower, upper)
+    }
+}
+
+// 文本内容里的 < > & 要转义
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Notation for MathMl {
+    fn number(&self, text: &str) -> String {
+        format!("<mn>{}</mn>", text)
+    }
+
+    fn identifier(&self, name: &str) -> String {
+        match (name, greek(name)) {
+            ("inf", _) => "<mi>∞</mi>".to_string(),
+            (_, Some((_, letter))) => format!("<mi>{}</mi>", letter),
+            _ => format!("<mi>{}</mi>", escape(name)),
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        format!("<mtext>{}</mtext>", escape(text))
+    }
+
+    fn operator(&self, symbol: &str) -> String {
+        let symbol = match symbol {
+            "-" | "neg" => "−",
+            "*" => "⋅",
+            "<=" => "≤",
+            ">=" => "≥",
+            "==" => "=",
+            "!=" => "≠",
+            "and" => "∧",
+            "or" => "∨",
+            "not" => "¬",
+            other => other,
+        };
+        format!("<mo>{}</mo>", escape(symbol))
+    }
+
+    fn row(&self, parts: Vec<String>) -> String {
+        format!("<mrow>{}</mrow>", parts.concat())
+    }
+
+    fn fenced(&self, inner: String, open: &str, close: &str) -> String {
+        format!("<mrow><mo>{}</mo>{}<mo>{}</mo></mrow>", open, inner, close)
+    }
+
+    fn fraction(&self, numerator: String, denominator: String) -> String {
+        format!("<mfrac>{}{}</mfrac>", numerator, denominator)
+    }
+
+    fn power(&self, base: String, exponent: String) -> String {
+        format!("<msup>{}{}</msup>", base, exponent)
+    }
+
+    fn root(&self, inner: String) -> String {
+        format!("<msqrt>{}</msqrt>", inner)
+    }
+
+    fn function(&self, name: &str) -> String {
+        // U+2061 是不可见的“函数作用”运算符
+        format!("<mi>{}</mi><mo>\u{2061}</mo>", escape(name))
+    }
+
+    fn matrix(&self, rows: Vec<Vec<String>>) -> String {
+        let rows: String = rows
+            .into_iter()
+            .map(|row| {
+                let cells: String = row
+                    .into_iter()
+                    .map(|cell| format!("<mtd>{}</mtd>", cell))
+                    .collect();
+                format!("<mtr>{}</mtr>", cells)
+            })
+            .collect();
+        self.fenced(format!("<mtable>{}</mtable>", rows), "[", "]")
+    }
+
+    fn cases(&self, cases: Vec<(String, Option<String>)>) -> String {
+        let rows: String = cases
+            .into_iter()
+            .map(|(value, condition)| {
+                let condition = match condition {
+                    Some(condition) => format!("<mtext>if </mtext>{}", condition),
+                    None => "<mtext>otherwise</mtext>".to_string(),
+                };
+                format!(
+                    "<mtr><mtd>{}</mtd><mtd><mrow>{}</mrow></mtd></mtr>",
+                    value, condition
+                )
+            })
+            .collect();
+        format!(
+            "<mrow><mo>{{</mo><mtable columnalign=\"left\">{}</mtable></mrow>",
+            rows
+        )
+    }
+
+    fn big(&self, symbol: &str, lower: String, upper: String) -> String {
+        format!(
+            "<munderover><mo>{}</mo>{}{}</munderover>",
+            symbol, lower, upper
+        )
+    }
+}
@@ -0,0 +1,173 @@
+// 特殊形式：参数不按普通函数那样先求值，而是绑定变量后反复求值
+use num::{BigInt, ToPrimitive};
+
+use super::ast::Expr;
+use super::bytecode;
+use super::error::CalcError;
+use super::eval::{condition, eval, Context};
+use super::export::latex_expr;
+use super::value::Value;
+use crate::functions::{integrate, Polynomial};
+
+// sum/prod 最多展开的项数
+const MAX_TERMS: u64 = 1_000_000;
+
+// 需要控制参数求值方式的函数，返回 None 表示按普通函数处理
+pub fn special_form(
+    name: &str,
+    args: &[Expr],
+    ctx: &mut Context,
+) -> Option<Result<Value, CalcError>> {
+    match name {
+        "poly" => Some(poly_form(args, ctx)),
+        "integrate" => Some(integrate_form(args, ctx)),
+        "sum" => Some(series_form("sum", args, ctx)),
+        "prod" => Some(series_form("prod", args, ctx)),
+        "piecewise" => Some(piecewise_form(args, ctx)),
+        "latex" => Some(latex_form(args)),
+        _ => None,
+    }
+}
+
+pub fn is_special_form(name: &str) -> bool {
+    matches!(
+        name,
+        "poly" | "integrate" | "sum" | "prod" | "piecewise" | "latex"
+    )
+}
+
+// 取出绑定变量名参数，比如 poly(expr, x) 里的 x
+fn variable_name<'a>(func: &str, arg: &'a Expr) -> Result<&'a str, CalcError> {
+    match arg {
+        Expr::Variable(name) => Ok(name),
+        _ => Err(CalcError::new(format!(
+            "{}: expected a variable name",
+            func
+        ))),
+    }
+}
+
+// poly([1, -3, 2]) 或 poly(x^2 - 3*x + 2, x)：把变量绑定为多项式 x 再求值
+fn poly_form(args: &[Expr], ctx: &mut Context) -> Result<Value, CalcError> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(arity_error("poly", "1 to 2", args.len()));
+    }
+    let var = match args.get(1) {
+        Some(arg) => variable_name("poly", arg)?,
+        None => "x",
+    };
+    let identity = Value::Poly(Polynomial::identity(var));
+    let value = ctx.with_binding(var, identity, |ctx| eval(&args[0], ctx))?;
+    match value {
+        Value::Poly(p) => Ok(Value::Poly(p)),
+        Value::List(items) => {
+            let coeffs: Vec<f64> = items.iter().map(Value::to_f64).collect();
+            if coeffs.iter().any(|c| !c.is_finite()) {
+                return Err(CalcError::new("poly: coefficients must be real numbers"));
+            }
+            Ok(Value::Poly(Polynomial::from_descending(&coeffs, var)))
+        }
+        other if other.to_f64().is_finite() => {
+            Ok(Value::Poly(Polynomial::constant(other.to_f64(), var)))
+        }
+        other => Err(CalcError::new(format!(
+            "poly: cannot convert {} to a polynomial",
+            other
+        ))),
+    }
+}
+
+fn arity_error(func: &str, expected: &str, got: usize) -> CalcError {
+    CalcError::new(format!(
+        "{}() takes {} argument(s), got {}",
+        func, expected, got
+    ))
+}
+
+// integrate(expr, x, a, b)：自适应 Gauss-Kronrod，积分限可以是 ±inf
+fn integrate_form(args: &[Expr], ctx: &mut Context) -> Result<Value, CalcError> {
+    if args.len() != 4 {
+        return Err(arity_error("integrate", "4", args.len()));
+    }
+    let var = variable_name("integrate", &args[1])?;
+    let a = eval(&args[2], ctx)?.to_f64();
+    let b = eval(&args[3], ctx)?.to_f64();
+    // 被积函数能编译时走字节码，得到 NaN 的点再按语法树求值一次，错误信息和原来一样
+    let program = bytecode::compile(&args[0], ctx, &[var]).ok();
+    let f = |x: f64| {
+        if let Some(y) = program
+            .as_ref()
+            .map(|p| p.run(&[x]))
+            .filter(|y| !y.is_nan())
+        {
+            return Ok(y);
+        }
+        ctx.with_binding(var, Value::Float(x), |ctx| eval(&args[0], ctx))
+            .map(|v| v.to_f64())
+    };
+    let (value, error) = integrate(f, a, b)?;
+    Ok(Value::Estimate(value, error))
+}
+
+// sum(expr, k, a, b) / prod(expr, k, a, b)：k 取 a 到 b 的整数，整数项保持精确
+fn series_form(func: &str, args: &[Expr], ctx: &mut Context) -> Result<Value, CalcError> {
+    if args.len() != 4 {
+        return Err(arity_error(func, "4", args.len()));
+    }
+    let var = variable_name(func, &args[1])?;
+    let bound = |expr: &Expr, ctx: &mut Context| -> Result<BigInt, CalcError> {
+        eval(expr, ctx)?
+            .to_integer()
+            .map_err(|e| CalcError::new(format!("{}: {}", func, e)))
+    };
+    let lo = bound(&args[2], ctx)?;
+    let hi = bound(&args[3], ctx)?;
+    let count = (&hi - &lo + 1u32).to_u64().unwrap_or(0);
+    if hi >= lo && (count == 0 || count > MAX_TERMS) {
+        return Err(CalcError::new(format!(
+            "{}: at most {} terms are supported",
+            func, MAX_TERMS
+        )));
+    }
+    let product = func == "prod";
+    let mut acc = Value::Int(BigInt::from(product as u8));
+    let mut k = lo;
+    while k <= hi {
+        let term = ctx.with_binding(var, Value::Int(k.clone()), |ctx| eval(&args[0], ctx))?;
+        acc = if product {
+            acc.mul(&term)?
+        } else {
+            acc.add(&term)?
+        };
+        k += 1;
+    }
+    Ok(acc)
+}
+
+// piecewise(c1, v1, c2, v2, ..., [默认值])：返回第一个成立条件对应的值，
+// 后面的条件和值都不求值
+fn piecewise_form(args: &[Expr], ctx: &mut Context) -> Result<Value, CalcError> {
+    if args.len() < 2 {
+        return Err(arity_error("piecewise", "at least 2", args.len()));
+    }
+    for pair in args.chunks(2) {
+        match pair {
+            [cond, value] => {
+                if condition(cond, ctx)? {
+                    return eval(value, ctx);
+                }
+            }
+            [default] => return eval(default, ctx),
+            _ => unreachable!(),
+        }
+    }
+    Err(CalcError::new("piecewise: no condition matched"))
+}
+
+// latex(expr)：不求值，返回表达式的 LaTeX 写法
+fn latex_form(args: &[Expr]) -> Result<Value, CalcError> {
+    match args {
+        [expr] => Ok(Value::Text(latex_expr(expr))),
+        _ => Err(arity_error("latex", "1", args.len())),
+    }
+}
@@ -0,0 +1,163 @@
+use std::ops::Range;
+
+use nom::branch::alt;
+use nom::bytes::complete::{take_while, take_while1};
+use nom::character::complete::{char, digit0, digit1, multispace0, one_of};
+use nom::combinator::{opt, recognize};
+use nom::error::{Error, ErrorKind};
+use nom::{IResult, Parser};
+
+use super::error::CalcError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+// 数字：12、3.5、.5、1e9、2.5E-3
+// 数字在输入末尾时 recognize 会少取最后几个字符（比如 "1.5" 得到 "1."），
+// 所以按剩余的输入自己切出来
+fn number(input: &str) -> IResult<&str, &str> {
+    let (rest, _) = (
+        alt((
+            recognize((digit1, opt((char('.'), digit0)))),
+            recognize((char('.'), digit1)),
+        )),
+        opt((one_of("eE"), opt(one_of("+-")), digit1)),
+    )
+        .parse(input)?;
+    Ok((rest, &input[..input.len() - rest.len()]))
+}
+
+// 标识符：函数名或变量名
+fn identifier(input: &str) -> IResult<&str, &str> {
+    recognize((
+        take_while1(|c: char| c.is_alphabetic() || c == '_'),
+        take_while(|c: char| c.is_alphanumeric() || c == '_'),
+    ))
+    .parse(input)
+}
+
+// 工作表的单元格引用：$1、$2 ...，当作变量名
+fn cell_reference(input: &str) -> IResult<&str, &str> {
+    recognize((char('$'), digit1)).parse(input)
+}
+
+// 多字符的运算符放在前面，保证最长匹配
+const OPERATORS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "!!", "<", ">", "=", "!", "+", "-", "*", "/", "%", "^", "?", ":",
+];
+
+// 运算符的别名：** 表示乘方，以及数学排版里常见的 × ÷ −
+const ALIASES: [(&str, &str); 4] = [("**", "^"), ("×", "*"), ("÷", "/"), ("−", "-")];
+
+fn operator(input: &str) -> IResult<&str, Token> {
+    for (alias, op) in ALIASES {
+        if let Some(rest) = input.strip_prefix(alias) {
+            return Ok((rest, Token::Op(op)));
+        }
+    }
+    for op in OPERATORS {
+        if let Some(rest) = input.strip_prefix(op) {
+            return Ok((rest, Token::Op(op)));
+        }
+    }
+    Err(nom::Err::Error(Error::new(input, ErrorKind::Tag)))
+}
+
+// 中文输入法打出的全角字符（１２３、（）、＋ 等）换成对应的半角字符，
+// 同时记下每个字节在原始输入里的位置，报错时可以指回原文
+fn normalize(input: &str) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(input.len());
+    let mut offsets = Vec::with_capacity(input.len() + 1);
+    for (pos, c) in input.char_indices() {
+        let c = match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        };
+        text.push(c);
+        offsets.extend(std::iter::repeat_n(pos, c.len_utf8()));
+    }
+    offsets.push(input.len());
+    (text, offsets)
+}
+
+fn token(input: &str) -> IResult<&str, Token> {
+    alt((
+        number.map(|s: &str| Token::Number(s.to_string())),
+        identifier.map(|s: &str| Token::Ident(s.to_string())),
+        cell_reference.map(|s: &str| Token::Ident(s.to_string())),
+        char('(').map(|_| Token::LParen),
+        char(')').map(|_| Token::RParen),
+        char('[').map(|_| Token::LBracket),
+        char(']').map(|_| Token::RBracket),
+        char(',').map(|_| Token::Comma),
+        operator,
+    ))
+    .parse(input)
+}
+
+// 词法分析的结果，spans[i] 是 tokens[i] 在原始输入里的字节范围
+#[derive(Debug, Default)]
+pub struct Lexed {
+    pub tokens: Vec<Token>,
+    pub spans: Vec<Range<usize>>,
+}
+
+pub fn tokenize(input: &str) -> Result<Lexed, CalcError> {
+    match scan(input) {
+        (lexed, None) => Ok(lexed),
+        (_, Some(error)) => Err(error),
+    }
+}
+
+// 尽量往后扫描，遇到无法识别的字符时停下并返回已经识别的部分，语法高亮也用它
+pub fn scan(input: &str) -> (Lexed, Option<CalcError>) {
+    let (text, offsets) = normalize(input);
+    let mut lexed = Lexed::default();
+    let mut rest = text.as_str();
+    loop {
+        // multispace0 不会失败
+        let (after_space, _) = multispace0::<&str, ()>(rest).unwrap_or((rest, ""));
+        rest = after_space;
+        if rest.is_empty() {
+            return (lexed, None);
+        }
+        let start = text.len() - rest.len();
+        match token(rest) {
+            Ok((next, tok)) => {
+                let end = text.len() - next.len();
+                lexed.tokens.push(tok);
+                lexed.spans.push(offsets[start]..offsets[end]);
+                rest = next;
+            }
+            Err(_) => {
+                let c = rest.chars().next().unwrap_or_default();
+                let error = CalcError::new(format!("unexpected character '{}'", c))
+                    .with_span(offsets[start]..offsets[start + c.len_utf8()]);
+                return (lexed, Some(error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::number;
+
+    #[test]
+    fn number_at_end_of_input() {
+        for input in ["1.5", ".5", "2.5E-3", "120.50", "7"] {
+            assert_eq!(number(input), Ok(("", input)));
+        }
+        assert_eq!(number("1.5)"), Ok((")", "1.5")));
+    }
+}
@@ -0,0 +1,84 @@
+// 表达式引擎：词法分析 -> 语法分析 -> 求值
+mod ast;
+mod bytecode;
+mod error;
+mod eval;
+mod export;
+mod forms;
+mod lexer;
+mod parser;
+mod trace;
+mod value;
+
+use std::collections::BTreeSet;
+
+pub use ast::{BinaryOp, Expr, Statement, UnaryOp};
+pub use bytecode::Program;
+pub use error::CalcError;
+pub use eval::Context;
+pub use export::{latex, mathml};
+pub use lexer::{scan, Token};
+pub use parser::KEYWORDS;
+pub use trace::Trace;
+pub use value::{Value, MAX_INT_BITS};
+
+/// 解析并执行一行输入：表达式、赋值 `x = 1` 或函数定义 `f(x) = x^2`
+pub fn evaluate(input: &str, ctx: &mut Context) -> Result<Value, CalcError> {
+    let statement = parse(input, ctx)?;
+    eval::eval_statement(&statement, ctx)
+}
+
+/// 把 var 绑定为 x 后按语法树求值，不重新解析；编译不了或者编译后的结果是 NaN 时用它
+pub fn evaluate_at(
+    expr: &Expr,
+    ctx: &mut Context,
+    var: &str,
+    x: Value,
+) -> Result<Value, CalcError> {
+    ctx.with_binding(var, x, |ctx| eval::eval(expr, ctx))
+}
+
+/// 把表达式编译成字节码，params 是运行时按顺序传入的变量，其他变量取上下文里现在的值
+pub fn compile(expr: &Expr, ctx: &Context, params: &[&str]) -> Result<Program, CalcError> {
+    bytecode::compile(expr, ctx, params)
+}
+
+/// 只解析不求值，隐式乘法和哪些名字是函数都按上下文
+pub fn parse(input: &str, ctx: &Context) -> Result<Statement, CalcError> {
+    let lexed = lexer::tokenize(input)?;
+    let lookup = |name: &str| {
+        if crate::functions::lookup(name).is_some()
+            || forms::is_special_form(name)
+            || ctx.user_function(name).is_some()
+        {
+            parser::Name::Function
+        } else if ctx.variable(name).is_some() {
+            parser::Name::Variable
+        } else {
+            parser::Name::Unknown
+        }
+    };
+    parser::parse(&lexed, ctx.implicit_multiplication(), &lookup)
+}
+
+/// 把一行输入的求值过程拆成一步步的改写：`2 + 3 * 4` → `2 + 12` → `14`，不改变上下文
+pub fn trace(input: &str, ctx: &Context) -> Result<Trace, CalcError> {
+    Ok(trace::trace(&parse(input, ctx)?, ctx))
+}
+
+/// 一行输入定义了什么、用到了哪些名字，工作表用来建立依赖关系
+pub struct Dependencies {
+    pub defines: Option<String>,
+    // 定义的是函数而不是变量
+    pub function: bool,
+    pub uses: BTreeSet<String>,
+}
+
+pub fn dependencies(input: &str, ctx: &Context) -> Result<Dependencies, CalcError> {
+    let statement = parse(input, ctx)?;
+    Ok(Dependencies {
+        defines: statement.defines().map(str::to_string),
+        function: matches!(statement, Statement::Define(..)),
+        uses: statement.uses(),
+    })
+}
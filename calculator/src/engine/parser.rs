@@ -0,0 +1,557 @@
+use std::ops::Range;
+
+use super::ast::{BinaryOp, Expr, Statement, UnaryOp};
+use super::error::CalcError;
+use super::lexer::{Lexed, Token};
+use super::value::Value;
+
+// 递归下降解析，优先级从低到高：
+//   if/三元 < or < and < not < 比较 < 加减 < 乘除取余 < 一元负号
+//   < 隐式乘法 < 百分号 < 乘方（右结合）< 后缀阶乘
+// 隐式乘法比 * / 优先，所以 1/2x = 1/(2x)，-2x = -(2x)，2x^2 = 2(x^2)
+struct Parser<'a> {
+    tokens: &'a [Token],
+    spans: &'a [Range<usize>],
+    pos: usize,
+    implicit_multiplication: bool,
+    // 名字后面跟左括号时，是函数才当作调用，是变量就是隐式乘法：x(2) = x*2
+    lookup: &'a dyn Fn(&str) -> Name,
+    // 解析函数体时是函数的参数，函数体里还没定义的名字也当作调用，
+    // 这样可以递归，也可以调用后面才定义的函数
+    params: Option<Vec<String>>,
+    // 当前括号、负号、乘方嵌套的层数
+    depth: usize,
+}
+
+// 每层括号要经过十几层递归下降，调试版在 1 MB 的栈上也要能解析到这个深度
+const MAX_DEPTH: usize = 64;
+
+// 关键字不能用作变量名或函数名
+pub const KEYWORDS: [&str; 10] = [
+    "if", "then", "else", "and", "or", "not", "true", "false", "mod", "of",
+];
+
+// 名字在上下文里的含义
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Name {
+    Function,
+    Variable,
+    Unknown,
+}
+
+pub fn parse(
+    lexed: &Lexed,
+    implicit_multiplication: bool,
+    lookup: &dyn Fn(&str) -> Name,
+) -> Result<Statement, CalcError> {
+    let mut parser = Parser {
+        tokens: &lexed.tokens,
+        spans: &lexed.spans,
+        pos: 0,
+        implicit_multiplication,
+        lookup,
+        params: None,
+        depth: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err(CalcError::new("empty expression"));
+    }
+    let statement = parser.statement()?;
+    match parser.peek() {
+        None => Ok(statement),
+        Some(tok) => Err(parser.error(parser.pos, format!("unexpected {}", describe(tok)))),
+    }
+}
+
+fn describe(tok: &Token) -> String {
+    match tok {
+        Token::Number(s) | Token::Ident(s) => format!("'{}'", s),
+        Token::Op(op) => format!("'{}'", op),
+        Token::LParen => "'('".to_string(),
+        Token::RParen => "')'".to_string(),
+        Token::LBracket => "'['".to_string(),
+        Token::RBracket => "']'".to_string(),
+        Token::Comma => "','".to_string(),
+    }
+}
+
+impl Parser<'_> {
+    // 错误标在第 index 个记号上，越过末尾时标在最后一个记号上
+    fn error(&self, index: usize, message: impl Into<String>) -> CalcError {
+        let error = CalcError::new(message);
+        match self.spans.get(index).or(self.spans.last()) {
+            Some(span) => error.with_span(span.clone()),
+            None => error,
+        }
+    }
+
+    // 赋值或定义的左边不能出现关键字和单元格引用
+    fn check_names(&self, end: usize) -> Result<(), CalcError> {
+        for (i, tok) in self.tokens[..end].iter().enumerate() {
+            if let Token::Ident(name) = tok {
+                if KEYWORDS.contains(&name.as_str()) {
+                    return Err(self.error(i, format!("'{}' is a reserved word", name)));
+                }
+                if name.starts_with('$') {
+                    return Err(self.error(i, format!("cannot assign to cell {}", name)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let tok = self.tokens.get(self.pos);
+        self.pos += 1;
+        tok
+    }
+
+    fn eat_op(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CalcError> {
+        match self.next().cloned() {
+            Some(tok) if tok == expected => Ok(()),
+            Some(tok) => Err(self.error(
+                self.pos - 1,
+                format!("expected {}, found {}", describe(&expected), describe(&tok)),
+            )),
+            None => Err(self.error(
+                self.pos,
+                format!("expected {}, found end of input", describe(&expected)),
+            )),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), CalcError> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(tok) => Err(self.error(
+                self.pos,
+                format!("expected '{}', found {}", keyword, describe(tok)),
+            )),
+            None => Err(self.error(
+                self.pos,
+                format!("expected '{}', found end of input", keyword),
+            )),
+        }
+    }
+
+    // 每进一层嵌套都经过这里，超过 MAX_DEPTH 时报错而不是栈溢出
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Expr, CalcError>,
+    ) -> Result<Expr, CalcError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(self.pos, "expression nested too deeply"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // name = expr 是赋值，f(x, y) = expr 是函数定义，其余是表达式
+    fn statement(&mut self) -> Result<Statement, CalcError> {
+        if let Some(Token::Ident(name)) = self.peek().cloned() {
+            if self.tokens.get(1) == Some(&Token::Op("=")) {
+                self.check_names(1)?;
+                self.pos = 2;
+                return Ok(Statement::Assign(name, self.expression()?));
+            }
+            if let Some(params) = self.definition_params() {
+                self.check_names(self.pos)?;
+                self.params = Some(params.clone());
+                return Ok(Statement::Define(name, params, self.expression()?));
+            }
+        }
+        Ok(Statement::Expr(self.expression()?))
+    }
+
+    // 识别 f(a, b) = 的形式，成功时跳到等号之后
+    fn definition_params(&mut self) -> Option<Vec<String>> {
+        if self.tokens.get(1) != Some(&Token::LParen) {
+            return None;
+        }
+        let mut params = Vec::new();
+        let mut i = 2;
+        if self.tokens.get(i) != Some(&Token::RParen) {
+            loop {
+                match self.tokens.get(i)? {
+                    Token::Ident(param) => params.push(param.clone()),
+                    _ => return None,
+                }
+                match self.tokens.get(i + 1)? {
+                    Token::Comma => i += 2,
+                    Token::RParen => {
+                        i += 1;
+                        break;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        if self.tokens.get(i + 1) != Some(&Token::Op("=")) {
+            return None;
+        }
+        self.pos = i + 2;
+        Some(params)
+    }
+
+    fn expression(&mut self) -> Result<Expr, CalcError> {
+        self.nested(Self::conditional)
+    }
+
+    fn conditional(&mut self) -> Result<Expr, CalcError> {
+        if self.eat_keyword("if") {
+            let cond = self.expression()?;
+            self.expect_keyword("then")?;
+            let then = self.expression()?;
+            self.expect_keyword("else")?;
+            let otherwise = self.expression()?;
+            return Ok(Expr::If(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+        let cond = self.or()?;
+        if self.eat_op(&["?"]).is_some() {
+            let then = self.expression()?;
+            self.expect(Token::Op(":"))?;
+            let otherwise = self.expression()?;
+            return Ok(Expr::If(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+        Ok(cond)
+    }
+
+    fn or(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.and()?;
+        while self.eat_keyword("or") {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.not()?;
+        while self.eat_keyword("and") {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, CalcError> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.nested(Self::not)?)));
+        }
+        self.comparison()
+    }
+
+    // 比较不能连写，a < b < c 要写成 a < b and b < c
+    fn comparison(&mut self) -> Result<Expr, CalcError> {
+        let lhs = self.additive()?;
+        let op = match self.eat_op(&["<", "<=", ">", ">=", "==", "!="]) {
+            Some("<") => BinaryOp::Lt,
+            Some("<=") => BinaryOp::Le,
+            Some(">") => BinaryOp::Gt,
+            Some(">=") => BinaryOp::Ge,
+            Some("==") => BinaryOp::Eq,
+            Some(_) => BinaryOp::Ne,
+            None => return Ok(lhs),
+        };
+        let rhs = self.additive()?;
+        if let Some(Token::Op(next)) = self.peek() {
+            if ["<", "<=", ">", ">=", "==", "!="].contains(next) {
+                return Err(self.error(self.pos, "comparisons cannot be chained, use 'and'"));
+            }
+        }
+        Ok(Expr::Binary(op, Box::new(lhs), Box::new(rhs)))
+    }
+
+    fn additive(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.term()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let op = if op == "+" {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.eat_op(&["*", "/"]) {
+                Some("*") => BinaryOp::Mul,
+                Some(_) => BinaryOp::Div,
+                None if self.eat_keyword("mod") => BinaryOp::Rem,
+                None => break,
+            };
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    // -2^2 = -4，与 meval 保持一致
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        match self.eat_op(&["-", "+"]) {
+            Some("-") => Ok(Expr::Unary(
+                UnaryOp::Neg,
+                Box::new(self.nested(Self::unary)?),
+            )),
+            Some(_) => self.nested(Self::unary),
+            None => self.implicit_product(),
+        }
+    }
+
+    // 2x、3(4+5)、(a+b)(a-b)、2π：后面紧跟标识符或左括号时视为相乘
+    fn implicit_product(&mut self) -> Result<Expr, CalcError> {
+        let mut lhs = self.percent()?;
+        while self.starts_factor() {
+            if !self.implicit_multiplication {
+                let tok = self.peek().map(describe).unwrap_or_default();
+                return Err(self.error(
+                    self.pos,
+                    format!(
+                        "missing operator before {} (implicit multiplication is off)",
+                        tok
+                    ),
+                ));
+            }
+            let rhs = self.percent()?;
+            lhs = Expr::Binary(BinaryOp::Mul, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn starts_factor(&self) -> bool {
+        match self.peek() {
+            Some(Token::LParen) => true,
+            Some(Token::Ident(name)) => !KEYWORDS.contains(&name.as_str()),
+            _ => false,
+        }
+    }
+
+    // 10% = 0.1，50% of 80 = 40；a + b% 和 a - b% 的含义在求值时处理
+    fn percent(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.power()?;
+        if self.eat_op(&["%"]).is_none() {
+            return Ok(expr);
+        }
+        let percent = Expr::Percent(Box::new(expr));
+        if self.eat_keyword("of") {
+            let base = self.nested(Self::unary)?;
+            return Ok(Expr::Binary(
+                BinaryOp::Mul,
+                Box::new(percent),
+                Box::new(base),
+            ));
+        }
+        if matches!(self.peek(), Some(Token::Number(_))) || self.starts_factor() {
+            return Err(self.error(
+                self.pos - 1,
+                "'%' means percent, use 'mod' for the remainder",
+            ));
+        }
+        Ok(percent)
+    }
+
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.postfix()?;
+        if self.eat_op(&["^"]).is_some() {
+            let exponent = self.nested(Self::unary)?;
+            return Ok(Expr::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    // 5! 是阶乘，7!! 是双阶乘
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.primary()?;
+        let func = match self.eat_op(&["!", "!!"]) {
+            Some("!") => "factorial",
+            Some(_) => "dfact",
+            None => return Ok(expr),
+        };
+        Ok(Expr::Call(func.to_string(), vec![expr]))
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        match self.next().cloned() {
+            Some(Token::Number(text)) => Value::parse_literal(&text)
+                .map(Expr::Literal)
+                .ok_or_else(|| self.error(self.pos - 1, format!("invalid number '{}'", text))),
+            Some(Token::Ident(name)) if name == "true" || name == "false" => {
+                Ok(Expr::Literal(Value::Bool(name == "true")))
+            }
+            Some(Token::Ident(name)) if KEYWORDS.contains(&name.as_str()) => {
+                Err(self.error(self.pos - 1, format!("unexpected '{}'", name)))
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LParen) && self.is_call(&name) {
+                    self.pos += 1;
+                    let args = self.arguments(Token::RParen)?;
+                    Ok(Expr::Call(name, args))
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            Some(Token::LBracket) => Ok(Expr::List(self.arguments(Token::RBracket)?)),
+            Some(Token::LParen) => {
+                let open = self.pos - 1;
+                let expr = self.expression()?;
+                if self.peek().is_none() {
+                    return Err(self.error(open, "missing ')'"));
+                }
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Some(tok) => Err(self.error(self.pos - 1, format!("unexpected {}", describe(&tok)))),
+            None => Err(self.error(self.pos, "unexpected end of input")),
+        }
+    }
+
+    fn is_call(&self, name: &str) -> bool {
+        if let Some(params) = &self.params {
+            if params.iter().any(|param| param == name) {
+                return false;
+            }
+        }
+        match (self.lookup)(name) {
+            Name::Function => true,
+            Name::Variable => false,
+            Name::Unknown => self.params.is_some(),
+        }
+    }
+
+    // 逗号分隔的参数或列表元素，已经消费了左括号
+    fn arguments(&mut self, close: Token) -> Result<Vec<Expr>, CalcError> {
+        let open = self.pos - 1;
+        let mut args = Vec::new();
+        if self.peek() == Some(&close) {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expression()?);
+            match self.next().cloned() {
+                Some(Token::Comma) => continue,
+                Some(tok) if tok == close => return Ok(args),
+                Some(tok) => {
+                    return Err(self.error(
+                        self.pos - 1,
+                        format!(
+                            "expected ',' or {}, found {}",
+                            describe(&close),
+                            describe(&tok)
+                        ),
+                    ))
+                }
+                None => return Err(self.error(open, format!("missing {}", describe(&close)))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lexer::tokenize;
+    use super::*;
+
+    fn parse_str(input: &str) -> Result<Statement, CalcError> {
+        let lookup = |name: &str| match name {
+            "sin" => Name::Function,
+            "a" => Name::Variable,
+            _ => Name::Unknown,
+        };
+        parse(&tokenize(input)?, true, &lookup)
+    }
+
+    #[test]
+    fn call_only_for_functions() {
+        let call = |name: &str, arg: Expr| Expr::Call(name.to_string(), vec![arg]);
+        let var = |name: &str| Expr::Variable(name.to_string());
+        let mul = |a, b| Expr::Binary(BinaryOp::Mul, Box::new(a), Box::new(b));
+        let two = || Expr::Literal(Value::Int(2.into()));
+        assert_eq!(
+            parse_str("sin(2)").unwrap(),
+            Statement::Expr(call("sin", two()))
+        );
+        assert_eq!(
+            parse_str("x(2)").unwrap(),
+            Statement::Expr(mul(var("x"), two()))
+        );
+        assert_eq!(
+            parse_str("f(x) = x(f(2))").unwrap(),
+            Statement::Define(
+                "f".to_string(),
+                vec!["x".to_string()],
+                mul(var("x"), call("f", two()))
+            )
+        );
+        // 函数体里可以调用后面才定义的函数，已定义的变量仍然是相乘
+        assert_eq!(
+            parse_str("f(x) = g(2) + a(2)").unwrap(),
+            Statement::Define(
+                "f".to_string(),
+                vec!["x".to_string()],
+                Expr::Binary(
+                    BinaryOp::Add,
+                    Box::new(call("g", two())),
+                    Box::new(mul(var("a"), two()))
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let nested = |n: usize| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+        assert!(parse_str(&nested(60)).is_ok());
+        for input in [
+            nested(100_000),
+            format!("{}1", "-".repeat(100_000)),
+            format!("{}1", "not ".repeat(100_000)),
+            "2^".repeat(100_000) + "2",
+        ] {
+            let error = parse_str(&input).unwrap_err();
+            assert_eq!(error.to_string(), "expression nested too deeply");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+// 逐步求值：每次只化简一处，把每一步的表达式都记下来，用于教学和调试
+use super::ast::{BinaryOp, Expr, Statement};
+use super::error::CalcError;
+use super::eval::{condition, eval, Context};
+use super::forms::is_special_form;
+use super::value::Value;
+use crate::functions;
+
+// 最多改写的次数，递归函数展开太多时截断
+const MAX_STEPS: usize = 200;
+// 整个过程最多求值的节点数
+const TRACE_BUDGET: u64 = 1_000_000;
+
+// 每一步化简后的整行输入，出错时 error 是停下来的原因
+pub struct Trace {
+    pub steps: Vec<String>,
+    pub error: Option<CalcError>,
+    // 超过 MAX_STEPS 没有算完
+    pub truncated: bool,
+}
+
+// 在上下文的副本上逐步求值，不改变变量；函数定义没有步骤
+pub fn trace(statement: &Statement, ctx: &Context) -> Trace {
+    let mut ctx = ctx.clone();
+    ctx.limit_steps(TRACE_BUDGET);
+    let (prefix, mut expr) = match statement {
+        Statement::Expr(expr) => (String::new(), expr.clone()),
+        Statement::Assign(name, expr) => (format!("{} = ", name), expr.clone()),
+        Statement::Define(..) => {
+            return Trace {
+                steps: vec![statement.to_string()],
+                error: None,
+                truncated: false,
+            }
+        }
+    };
+    let mut trace = Trace {
+        steps: vec![format!("{}{}", prefix, expr)],
+        error: None,
+        truncated: false,
+    };
+    let mut rewrites = 0;
+    while !matches!(expr, Expr::Literal(_)) {
+        rewrites += 1;
+        if rewrites > MAX_STEPS {
+            trace.truncated = true;
+            break;
+        }
+        // 先把已知的变量一次全部代入，再化简最左边最内层的一处
+        let changed = if substitute_variables(&mut expr, &ctx) {
+            Ok(true)
+        } else {
+            step(&mut expr, &mut ctx)
+        };
+        match changed {
+            Ok(true) => {
+                // -5 和 [2, 6] 化成值前后写法一样，不重复记
+                let line = format!("{}{}", prefix, expr);
+                if trace.steps.last() != Some(&line) {
+                    trace.steps.push(line);
+                }
+            }
+            Ok(false) => break,
+            Err(error) => {
+                trace.error = Some(error);
+                break;
+            }
+        }
+    }
+    trace
+}
+
+// 代入已知变量，特殊形式里的变量由它自己绑定，不动
+fn substitute_variables(expr: &mut Expr, ctx: &Context) -> bool {
+    match expr {
+        Expr::Variable(name) => match ctx.variable(name) {
+            Some(value) => {
+                *expr = Expr::Literal(value.clone());
+                true
+            }
+            None => false,
+        },
+        Expr::Call(name, _) if is_special_form(name) => false,
+        _ => {
+            let mut changed = false;
+            for child in children(expr) {
+                changed |= substitute_variables(child, ctx);
+            }
+            changed
+        }
+    }
+}
+
+fn children(expr: &mut Expr) -> Vec<&mut Expr> {
+    match expr {
+        Expr::Literal(_) | Expr::Variable(_) => Vec::new(),
+        Expr::Unary(_, operand) | Expr::Percent(operand) => vec![operand],
+        Expr::Binary(_, lhs, rhs) | Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => vec![lhs, rhs],
+        Expr::If(cond, then, otherwise) => vec![cond, then, otherwise],
+        Expr::Call(_, args) | Expr::List(args) => args.iter_mut().collect(),
+    }
+}
+
+// 化简一处，返回是否有变化；子表达式都是值了才化简自己
+fn step(expr: &mut Expr, ctx: &mut Context) -> Result<bool, CalcError> {
+    let is_or = matches!(expr, Expr::Or(..));
+    match expr {
+        Expr::Literal(_) => Ok(false),
+        Expr::Variable(_) | Expr::Unary(..) | Expr::Percent(_) | Expr::List(_) => {
+            for child in children(expr) {
+                if step(child, ctx)? {
+                    return Ok(true);
+                }
+            }
+            reduce(expr, ctx)
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            if step(lhs, ctx)? {
+                return Ok(true);
+            }
+            // a + b% 要整体计算，b% 本身不能先化成 b/100
+            let rhs = match (*op, &mut **rhs) {
+                (BinaryOp::Add | BinaryOp::Sub, Expr::Percent(percent)) => percent,
+                (_, rhs) => rhs,
+            };
+            if step(rhs, ctx)? {
+                return Ok(true);
+            }
+            reduce(expr, ctx)
+        }
+        // and / or 短路：左边已经决定结果时不再看右边
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            if step(lhs, ctx)? {
+                return Ok(true);
+            }
+            let left = condition(lhs, ctx)?;
+            if left == is_or {
+                *expr = Expr::Literal(Value::Bool(left));
+                return Ok(true);
+            }
+            if step(rhs, ctx)? {
+                return Ok(true);
+            }
+            reduce(expr, ctx)
+        }
+        Expr::If(cond, then, otherwise) => {
+            if step(cond, ctx)? {
+                return Ok(true);
+            }
+            let branch = if condition(cond, ctx)? {
+                then
+            } else {
+                otherwise
+            };
+            *expr = std::mem::replace(&mut **branch, Expr::Literal(Value::Bool(false)));
+            Ok(true)
+        }
+        // 特殊形式自己控制参数的求值，一步算完
+        Expr::Call(name, _) if is_special_form(name) => reduce(expr, ctx),
+        Expr::Call(name, args) => {
+            for arg in args.iter_mut() {
+                if step(arg, ctx)? {
+                    return Ok(true);
+                }
+            }
+            if functions::lookup(name).is_none() {
+                if let Some((params, body)) = ctx.user_function(name) {
+                    if params.len() == args.len() {
+                        // 展开用户函数：参数代入函数体
+                        let mut body = body.clone();
+                        for (param, arg) in params.iter().zip(args.iter()) {
+                            bind(&mut body, param, arg);
+                        }
+                        *expr = body;
+                        return Ok(true);
+                    }
+                }
+            }
+            reduce(expr, ctx)
+        }
+    }
+}
+
+// 子表达式都是值了，直接求出这一处
+fn reduce(expr: &mut Expr, ctx: &mut Context) -> Result<bool, CalcError> {
+    *expr = Expr::Literal(eval(expr, ctx)?);
+    Ok(true)
+}
+
+// 把函数体里的参数换成实参，特殊形式自己绑定的同名变量不换
+fn bind(expr: &mut Expr, param: &str, arg: &Expr) {
+    match expr {
+        Expr::Variable(name) if name == param => *expr = arg.clone(),
+        Expr::Call(name, args) if is_special_form(name) => {
+            let bound = match (name.as_str(), args.get(1)) {
+                (_, Some(Expr::Variable(var))) if name != "piecewise" => Some(var.clone()),
+                ("poly", None) => Some("x".to_string()),
+                _ => None,
+            };
+            if bound.as_deref() == Some(param) {
+                // 被绑定的表达式和变量名不换，求和范围之类的照常代入
+                for arg_expr in args.iter_mut().skip(2) {
+                    bind(arg_expr, param, arg);
+                }
+            } else {
+                for arg_expr in args.iter_mut() {
+                    bind(arg_expr, param, arg);
+                }
+            }
+        }
+        _ => {
+            for child in children(expr) {
+                bind(child, param, arg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{evaluate, trace, Context};
+
+    // 代入的值写出来是一串时要加括号，否则步骤读起来是另一个算式
+    #[test]
+    fn substituted_values_keep_their_grouping() {
+        let mut ctx = Context::default();
+        evaluate("p = poly(x + 1)", &mut ctx).unwrap();
+        evaluate("e = integrate(x, x, 0, 1)", &mut ctx).unwrap();
+        for (input, step) in [
+            ("3*p", "3 * (x + 1)"),
+            ("p^2", "(x + 1)^2"),
+            ("2*e", "2 * (0.5 ± 0.0e0)"),
+        ] {
+            let steps = trace(input, &ctx).unwrap().steps;
+            assert_eq!(steps[1], step, "{}", input);
+        }
+    }
+}
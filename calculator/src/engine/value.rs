@@ -0,0 +1,335 @@
+use std::fmt::{Display, Formatter};
+
+use num::complex::Complex64;
+use num::{BigInt, FromPrimitive, One, Signed, ToPrimitive, Zero};
+
+use super::ast::BinaryOp;
+use crate::functions::Polynomial;
+
+// 整数结果超过这个位数就不再精确计算，避免界面卡死
+pub const MAX_INT_BITS: u64 = 1 << 20;
+// 多项式乘方结果的最高次数，同样是为了不卡住界面
+const MAX_POLY_DEGREE: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(BigInt),
+    Float(f64),
+    // 质因数分解的结果，负数时第一个因子是 -1
+    Factors(Vec<(BigInt, u32)>),
+    List(Vec<Value>),
+    Complex(Complex64),
+    Poly(Polynomial),
+    // 数值积分的结果和误差估计，参与运算时只取数值
+    Estimate(f64, f64),
+    // 比较和逻辑运算的结果，参与算术时当作 1 / 0
+    Bool(bool),
+    // 说明性的文本结果，比如函数定义成功的提示
+    Text(String),
+}
+
+impl Value {
+    // 没有小数点的字面量按整数处理，1e9 这种非负指数也算整数
+    pub fn parse_literal(text: &str) -> Option<Value> {
+        if !text.contains('.') {
+            let (mantissa, exponent) = match text.find(['e', 'E']) {
+                Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+                None => (text, 0),
+            };
+            if (0..=100_000).contains(&exponent) {
+                let mantissa: BigInt = mantissa.parse().ok()?;
+                return Some(Value::Int(
+                    mantissa * num::pow(BigInt::from(10), exponent as usize),
+                ));
+            }
+        }
+        text.parse::<f64>().ok().map(Value::Float)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Value::Int(n) => n.to_f64().unwrap_or(f64::NAN),
+            Value::Float(x) | Value::Estimate(x, _) => *x,
+            Value::Factors(_) => self
+                .to_integer()
+                .ok()
+                .and_then(|n| n.to_f64())
+                .unwrap_or(f64::NAN),
+            Value::Complex(z) if z.im == 0.0 => z.re,
+            Value::Bool(b) => *b as u8 as f64,
+            Value::List(_) | Value::Complex(_) | Value::Poly(_) | Value::Text(_) => f64::NAN,
+        }
+    }
+
+    pub fn to_complex(&self) -> Complex64 {
+        match self {
+            Value::Complex(z) => *z,
+            other => Complex64::new(other.to_f64(), 0.0),
+        }
+    }
+
+    // 需要整数参数的函数也接受 3.0 这样的浮点数
+    pub fn to_integer(&self) -> Result<BigInt, String> {
+        match self {
+            Value::Int(n) => Ok(n.clone()),
+            Value::Bool(b) => Ok(BigInt::from(*b as u8)),
+            Value::Float(x) if x.is_finite() && x.fract() == 0.0 => {
+                Ok(BigInt::from_f64(*x).unwrap_or_default())
+            }
+            Value::Float(x) => Err(format!("expected an integer, got {}", x)),
+            Value::Estimate(x, _) => Value::Float(*x).to_integer(),
+            Value::Factors(factors) => Ok(factors
+                .iter()
+                .map(|(p, k)| num::pow(p.clone(), *k as usize))
+                .product()),
+            other => Err(format!("expected an integer, got {}", other)),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<&[Value], String> {
+        match self {
+            Value::List(items) => Ok(items),
+            other => Err(format!("expected a list, got {}", other)),
+        }
+    }
+
+    // 条件判断：布尔值，或者数值非零即为真
+    pub fn truthy(&self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            Value::Complex(z) => Ok(!z.is_zero()),
+            Value::Int(n) => Ok(!n.is_zero()),
+            Value::Factors(_) => Ok(true),
+            Value::Float(x) | Value::Estimate(x, _) if !x.is_nan() => Ok(*x != 0.0),
+            other => Err(format!("cannot use {} as a condition", other)),
+        }
+    }
+
+    // 整数之间精确比较，其余按实数比较；==、!= 对复数、列表等按结构相等
+    pub fn compare(&self, op: BinaryOp, rhs: &Value) -> Result<Value, String> {
+        let real = self.is_real() && rhs.is_real();
+        let ordering = match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            _ if real => self.to_f64().partial_cmp(&rhs.to_f64()),
+            _ => None,
+        };
+        let equal = ordering.map_or(!real && self == rhs, |ord| ord.is_eq());
+        let result = match op {
+            BinaryOp::Eq => equal,
+            BinaryOp::Ne => !equal,
+            _ if !real => return Err(format!("cannot compare {} and {}", self, rhs)),
+            BinaryOp::Lt => ordering.is_some_and(|ord| ord.is_lt()),
+            BinaryOp::Le => ordering.is_some_and(|ord| ord.is_le()),
+            BinaryOp::Gt => ordering.is_some_and(|ord| ord.is_gt()),
+            _ => ordering.is_some_and(|ord| ord.is_ge()),
+        };
+        Ok(Value::Bool(result))
+    }
+
+    fn is_real(&self) -> bool {
+        match self {
+            Value::Int(_)
+            | Value::Float(_)
+            | Value::Factors(_)
+            | Value::Estimate(..)
+            | Value::Bool(_) => true,
+            Value::Complex(z) => z.im == 0.0,
+            _ => false,
+        }
+    }
+
+    // 实部以外可以忽略时返回实数
+    pub fn from_complex(z: Complex64) -> Value {
+        if z.im.abs() <= 1e-12 * z.re.abs().max(1.0) {
+            Value::Float(z.re)
+        } else {
+            Value::Complex(z)
+        }
+    }
+
+    pub fn neg(&self) -> Result<Value, String> {
+        Value::Int(BigInt::zero()).sub(self)
+    }
+
+    pub fn add(&self, rhs: &Value) -> Result<Value, String> {
+        Ok(match operands(self, rhs)? {
+            Operands::Int(a, b) => Value::Int(a + b),
+            Operands::Float(a, b) => Value::Float(a + b),
+            Operands::Complex(a, b) => Value::from_complex(a + b),
+            Operands::Poly(a, b) => Value::Poly(a.add(&b)),
+        })
+    }
+
+    pub fn sub(&self, rhs: &Value) -> Result<Value, String> {
+        Ok(match operands(self, rhs)? {
+            Operands::Int(a, b) => Value::Int(a - b),
+            Operands::Float(a, b) => Value::Float(a - b),
+            Operands::Complex(a, b) => Value::from_complex(a - b),
+            Operands::Poly(a, b) => Value::Poly(a.sub(&b)),
+        })
+    }
+
+    pub fn mul(&self, rhs: &Value) -> Result<Value, String> {
+        Ok(match operands(self, rhs)? {
+            Operands::Int(a, b) => Value::Int(a * b),
+            Operands::Float(a, b) => Value::Float(a * b),
+            Operands::Complex(a, b) => Value::from_complex(a * b),
+            Operands::Poly(a, b) => Value::Poly(a.mul(&b)),
+        })
+    }
+
+    // 能整除时保持整数，否则退化为浮点数；多项式只允许整除
+    pub fn div(&self, rhs: &Value) -> Result<Value, String> {
+        Ok(match operands(self, rhs)? {
+            Operands::Int(a, b) if !b.is_zero() && (&a % &b).is_zero() => Value::Int(a / b),
+            Operands::Int(a, b) => Value::Float(int_to_f64(&a) / int_to_f64(&b)),
+            Operands::Float(a, b) => Value::Float(a / b),
+            Operands::Complex(a, b) => Value::from_complex(a / b),
+            Operands::Poly(a, b) => {
+                let (q, r) = a.div_rem(&b)?;
+                if !r.is_zero() {
+                    return Err(format!("{} is not divisible by {}, use pdiv()", a, b));
+                }
+                Value::Poly(q)
+            }
+        })
+    }
+
+    pub fn rem(&self, rhs: &Value) -> Result<Value, String> {
+        Ok(match operands(self, rhs)? {
+            Operands::Int(a, b) if !b.is_zero() => Value::Int(a % b),
+            Operands::Int(a, b) => Value::Float(int_to_f64(&a) % int_to_f64(&b)),
+            Operands::Float(a, b) => Value::Float(a % b),
+            Operands::Complex(..) => return Err("remainder of complex numbers".to_string()),
+            Operands::Poly(a, b) => Value::Poly(a.div_rem(&b)?.1),
+        })
+    }
+
+    pub fn pow(&self, rhs: &Value) -> Result<Value, String> {
+        match operands(self, rhs)? {
+            Operands::Int(a, b) if !b.is_negative() => {
+                // 0、1、-1 的任意次幂不受位数限制
+                if a.abs() <= BigInt::one() {
+                    let result = if b.is_zero() || (a.is_negative() && !b.bit(0)) {
+                        BigInt::one()
+                    } else {
+                        a
+                    };
+                    return Ok(Value::Int(result));
+                }
+                let exp = b
+                    .to_u64()
+                    .filter(|e| a.bits().saturating_mul(*e) <= MAX_INT_BITS);
+                match exp {
+                    Some(e) => Ok(Value::Int(num::pow(a, e as usize))),
+                    None => Err("integer result too large".to_string()),
+                }
+            }
+            Operands::Int(a, b) => Ok(Value::Float(int_to_f64(&a).powf(int_to_f64(&b)))),
+            Operands::Float(a, b) => Ok(Value::Float(a.powf(b))),
+            Operands::Complex(a, b) => Ok(Value::from_complex(a.powc(b))),
+            Operands::Poly(a, _) => {
+                let exp = rhs
+                    .to_integer()
+                    .ok()
+                    .and_then(|e| e.to_u32())
+                    .ok_or("polynomial exponent must be a non-negative integer")?;
+                // 常数多项式按数值算，否则 exp 次乘法没有上限
+                if a.degree() == 0 {
+                    let c = a.coeffs().first().copied().unwrap_or(0.0);
+                    return Ok(Value::Poly(Polynomial::constant(
+                        c.powf(exp as f64),
+                        a.var(),
+                    )));
+                }
+                if (a.degree() as u64).saturating_mul(exp as u64) > MAX_POLY_DEGREE {
+                    return Err("polynomial result degree too large".to_string());
+                }
+                Ok(Value::Poly(a.pow(exp)))
+            }
+        }
+    }
+}
+
+fn int_to_f64(n: &BigInt) -> f64 {
+    n.to_f64().unwrap_or(f64::NAN)
+}
+
+// 二元运算前把两边提升到同一种类型：整数 < 浮点数 < 复数 < 多项式
+enum Operands {
+    Int(BigInt, BigInt),
+    Float(f64, f64),
+    Complex(Complex64, Complex64),
+    Poly(Polynomial, Polynomial),
+}
+
+fn operands(a: &Value, b: &Value) -> Result<Operands, String> {
+    let rank = |v: &Value| match v {
+        Value::Int(_) | Value::Factors(_) | Value::Bool(_) => Ok(0),
+        Value::Float(_) | Value::Estimate(..) => Ok(1),
+        Value::Complex(_) => Ok(2),
+        Value::Poly(_) => Ok(3),
+        Value::List(_) => Err("arithmetic on lists is not supported".to_string()),
+        Value::Text(_) => Err("arithmetic on text is not supported".to_string()),
+    };
+    Ok(match rank(a)?.max(rank(b)?) {
+        0 => Operands::Int(a.to_integer()?, b.to_integer()?),
+        1 => Operands::Float(a.to_f64(), b.to_f64()),
+        2 => Operands::Complex(a.to_complex(), b.to_complex()),
+        _ => {
+            let var = match (a, b) {
+                (Value::Poly(p), Value::Poly(q)) if p.var() != q.var() => {
+                    return Err(format!(
+                        "cannot combine polynomials in {} and {}",
+                        p.var(),
+                        q.var()
+                    ))
+                }
+                (Value::Poly(p), _) | (_, Value::Poly(p)) => p.var().to_string(),
+                _ => unreachable!(),
+            };
+            let to_poly = |v: &Value| match v {
+                Value::Poly(p) => Ok(p.clone()),
+                Value::Complex(_) => Err("complex polynomial coefficients are not supported"),
+                other => Ok(Polynomial::constant(other.to_f64(), &var)),
+            };
+            Operands::Poly(to_poly(a)?, to_poly(b)?)
+        }
+    })
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Factors(factors) if factors.is_empty() => write!(f, "1"),
+            Value::Factors(factors) => {
+                let parts: Vec<String> = factors
+                    .iter()
+                    .map(|(p, k)| {
+                        if *k == 1 {
+                            p.to_string()
+                        } else {
+                            format!("{}^{}", p, k)
+                        }
+                    })
+                    .collect();
+                write!(f, "{}", parts.join(" · "))
+            }
+            Value::List(items) => {
+                let parts: Vec<String> = items.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", parts.join(", "))
+            }
+            Value::Complex(z) if z.re == 0.0 => write!(f, "{}i", z.im),
+            Value::Complex(z) => {
+                let sign = if z.im < 0.0 { '-' } else { '+' };
+                write!(f, "{} {} {}i", z.re, sign, z.im.abs())
+            }
+            Value::Poly(p) => write!(f, "{}", p),
+            Value::Estimate(x, err) => write!(f, "{} ± {:.1e}", x, err),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
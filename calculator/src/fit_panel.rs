@@ -0,0 +1,311 @@
+use eframe::egui;
+
+use crate::clipboard::parse_columns;
+use crate::engine::{evaluate, Context, Value};
+use crate::functions::{fit, Fit, Model, MAX_DEGREE};
+
+// 画拟合曲线时取的点数
+const CURVE_SAMPLES: usize = 200;
+const PLOT_HEIGHT: f32 = 200.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Points,
+    Lists,
+}
+
+// "Fit" 面板：对 (x, y) 数据做最小二乘拟合，显示系数、R²、残差，并把曲线画在散点图上
+pub struct FitPanel {
+    source: Source,
+    // 每行一个点，x 和 y 用空白、逗号、分号或制表符隔开
+    points: String,
+    x_list: String,
+    y_list: String,
+    model: Model,
+    degree: usize,
+    // 把拟合结果定义成这个名字的函数
+    name: String,
+    data: Vec<(f64, f64)>,
+    fit: Option<Fit>,
+    status: Option<String>,
+}
+
+impl Default for FitPanel {
+    fn default() -> Self {
+        FitPanel {
+            source: Source::Points,
+            points: String::new(),
+            x_list: String::new(),
+            y_list: String::new(),
+            model: Model::Linear,
+            degree: 2,
+            name: "fit".to_string(),
+            data: Vec::new(),
+            fit: None,
+            status: None,
+        }
+    }
+}
+
+impl FitPanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &mut Context) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.source, Source::Points, "points");
+            ui.radio_value(&mut self.source, Source::Lists, "lists");
+        });
+        match self.source {
+            Source::Points => {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.points)
+                        .code_editor()
+                        .hint_text("x y\n1 2.1\n2 3.9\n…")
+                        .desired_rows(5)
+                        .desired_width(240.0),
+                );
+            }
+            Source::Lists => {
+                ui.horizontal(|ui| {
+                    ui.label("x");
+                    ui.add(egui::TextEdit::singleline(&mut self.x_list).desired_width(100.0));
+                    ui.label("y");
+                    ui.add(egui::TextEdit::singleline(&mut self.y_list).desired_width(100.0));
+                });
+            }
+        }
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("Model")
+                .selected_text(self.model.name())
+                .show_ui(ui, |ui| {
+                    for model in Model::ALL {
+                        let selected = model.name() == self.model.name();
+                        if ui.selectable_label(selected, model.name()).clicked() && !selected {
+                            self.model = model;
+                        }
+                    }
+                });
+            if let Model::Polynomial(_) = self.model {
+                ui.add(
+                    egui::DragValue::new(&mut self.degree)
+                        .range(2..=MAX_DEGREE)
+                        .prefix("degree "),
+                );
+            }
+            if ui.button("Fit").clicked() {
+                self.run(ctx);
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        let Some(fit) = &self.fit else {
+            return;
+        };
+
+        ui.monospace(fit.to_string());
+        egui::Grid::new("fit_coefficients")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (name, c) in fit.model.coefficient_names().iter().zip(&fit.coeffs) {
+                    ui.monospace(name);
+                    ui.monospace(c.to_string());
+                    ui.end_row();
+                }
+                ui.monospace("R²");
+                ui.monospace(format!("{:.6}", fit.r_squared));
+                ui.end_row();
+            });
+        plot(ui, &self.data, fit);
+
+        let mut define = false;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(60.0));
+            define = ui
+                .button(format!("Define {}(x)", self.name.trim()))
+                .on_hover_text(fit.expression())
+                .clicked();
+        });
+
+        ui.collapsing(format!("Residuals ({})", self.data.len()), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("fit_residuals")
+                        .striped(true)
+                        .num_columns(4)
+                        .show(ui, |ui| {
+                            for title in ["x", "y", "fitted", "residual"] {
+                                ui.strong(title);
+                            }
+                            ui.end_row();
+                            for (&(x, y), r) in self.data.iter().zip(&fit.residuals) {
+                                for value in [x, y, y - r, *r] {
+                                    ui.monospace(value.to_string());
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+
+        if define {
+            let definition = format!("{}(x) = {}", self.name.trim(), fit.expression());
+            self.status = Some(match evaluate(&definition, ctx) {
+                Ok(_) => format!("defined {}(x)", self.name.trim()),
+                Err(err) => format!("cannot define: {}", err),
+            });
+        }
+    }
+
+    fn run(&mut self, ctx: &Context) {
+        self.fit = None;
+        let data = match self.source {
+            Source::Points => points(&self.points),
+            Source::Lists => lists(&self.x_list, &self.y_list, ctx),
+        };
+        let (xs, ys) = match data {
+            Ok(data) => data,
+            Err(message) => {
+                self.status = Some(message);
+                return;
+            }
+        };
+        let model = match self.model {
+            Model::Polynomial(_) => Model::Polynomial(self.degree),
+            model => model,
+        };
+        match fit(model, &xs, &ys) {
+            Ok(fit) => {
+                self.data = xs.into_iter().zip(ys).collect();
+                self.fit = Some(fit);
+                self.status = None;
+            }
+            Err(message) => self.status = Some(message),
+        }
+    }
+}
+
+// 前两列分别是 x 和 y，表头可有可无
+fn points(text: &str) -> Result<(Vec<f64>, Vec<f64>), String> {
+    let mut columns = parse_columns(text).into_iter();
+    match (columns.next(), columns.next()) {
+        (Some((_, xs)), Some((_, ys))) => Ok((
+            xs.iter().map(Value::to_f64).collect(),
+            ys.iter().map(Value::to_f64).collect(),
+        )),
+        _ => Err("enter one x y pair per line".to_string()),
+    }
+}
+
+// 两个结果为列表的表达式，比如拖进来的 CSV 读成的列表变量
+fn lists(x: &str, y: &str, ctx: &Context) -> Result<(Vec<f64>, Vec<f64>), String> {
+    let mut scratch = ctx.clone();
+    let mut list = |label: &str, text: &str| {
+        let value = evaluate(text, &mut scratch).map_err(|e| format!("{}: {}", label, e))?;
+        let items = value.as_list().map_err(|e| format!("{}: {}", label, e))?;
+        Ok::<_, String>(items.iter().map(Value::to_f64).collect())
+    };
+    Ok((list("x", x)?, list("y", y)?))
+}
+
+// 散点和拟合曲线，坐标范围取数据的范围再留一点边
+fn plot(ui: &mut egui::Ui, data: &[(f64, f64)], fit: &Fit) {
+    let size = egui::vec2(ui.available_width().max(200.0), PLOT_HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+    let (x_lo, x_hi) = padded(data.iter().map(|p| p.0));
+    let curve: Vec<(f64, f64)> = (0..=CURVE_SAMPLES)
+        .map(|i| {
+            let x = x_lo + (x_hi - x_lo) * i as f64 / CURVE_SAMPLES as f64;
+            (x, fit.eval(x))
+        })
+        .collect();
+    // 曲线在数据范围外跑得很远时只按数据定纵轴
+    let (y_lo, y_hi) = padded(data.iter().map(|p| p.1));
+    let (y_lo, y_hi) = padded(
+        curve
+            .iter()
+            .map(|p| p.1)
+            .filter(|y| (2.0 * y_lo - y_hi..=2.0 * y_hi - y_lo).contains(y))
+            .chain([y_lo, y_hi]),
+    );
+    let to_screen = |(x, y): (f64, f64)| {
+        egui::pos2(
+            rect.left() + ((x - x_lo) / (x_hi - x_lo)) as f32 * rect.width(),
+            rect.bottom() - ((y - y_lo) / (y_hi - y_lo)) as f32 * rect.height(),
+        )
+    };
+
+    let axis = egui::Stroke::new(1.0, visuals.weak_text_color());
+    if (x_lo..=x_hi).contains(&0.0) {
+        let x = to_screen((0.0, y_lo)).x;
+        painter.line_segment(
+            [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+            axis,
+        );
+    }
+    if (y_lo..=y_hi).contains(&0.0) {
+        let y = to_screen((x_lo, 0.0)).y;
+        painter.line_segment(
+            [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+            axis,
+        );
+    }
+
+    // 对数、幂函数在 x ≤ 0 处没有定义，曲线在那里断开
+    let stroke = egui::Stroke::new(2.0, visuals.selection.bg_fill);
+    let mut segment = Vec::new();
+    for &point in &curve {
+        if point.1.is_finite() {
+            segment.push(to_screen(point));
+        } else if !segment.is_empty() {
+            painter.add(egui::Shape::line(std::mem::take(&mut segment), stroke));
+        }
+    }
+    if segment.len() > 1 {
+        painter.add(egui::Shape::line(segment, stroke));
+    }
+    for &point in data {
+        painter.circle_filled(to_screen(point), 3.0, visuals.text_color());
+    }
+
+    let font = egui::FontId::proportional(11.0);
+    let color = visuals.weak_text_color();
+    let corner = |pos, align, value: f64| {
+        painter.text(pos, align, format!("{:.3}", value), font.clone(), color);
+    };
+    corner(
+        rect.left_bottom() + egui::vec2(4.0, -2.0),
+        egui::Align2::LEFT_BOTTOM,
+        x_lo,
+    );
+    corner(
+        rect.right_bottom() + egui::vec2(-4.0, -2.0),
+        egui::Align2::RIGHT_BOTTOM,
+        x_hi,
+    );
+    corner(
+        rect.left_top() + egui::vec2(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        y_hi,
+    );
+}
+
+// 最小值和最大值各留 5% 的边；全都相同时左右各留 1
+fn padded(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+        (lo.min(v), hi.max(v))
+    });
+    if !(lo.is_finite() && hi.is_finite()) {
+        return (-1.0, 1.0);
+    }
+    if hi == lo {
+        return (lo - 1.0, hi + 1.0);
+    }
+    let pad = (hi - lo) * 0.05;
+    (lo - pad, hi + pad)
+}
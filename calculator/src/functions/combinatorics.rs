@@ -0,0 +1,223 @@
+// 组合数学函数，结果都是精确的大整数
+use num::{BigInt, One, Signed, ToPrimitive, Zero};
+
+use super::{Arity, Builtin, FnResult, PREVIEW_BITS};
+use crate::engine::{Value, MAX_INT_BITS};
+
+pub const FUNCTIONS: &[Builtin] = &[
+    Builtin::new("factorial", Arity::Exact(1), factorial_fn).slow(factorial_cost),
+    Builtin::new("dfact", Arity::Exact(1), dfact_fn).slow(dfact_cost),
+    Builtin::new("nCr", Arity::Exact(2), ncr_fn).slow(ncr_cost),
+    Builtin::new("nPr", Arity::Exact(2), npr_fn).slow(npr_cost),
+    Builtin::new("catalan", Arity::Exact(1), catalan_fn).slow(catalan_cost),
+    Builtin::new("fib", Arity::Exact(1), fib_fn).slow(fib_cost),
+];
+
+// 预览前按结果的位数估计开销，参数不合法时交给函数本身报错
+fn too_many_bits(args: &[Value], bits: fn(u64, u64) -> f64) -> bool {
+    let n = args.first().map_or(Ok(0), count);
+    let r = args.get(1).map_or(Ok(0), count);
+    match (n, r) {
+        (Ok(n), Ok(r)) => bits(n, r) > PREVIEW_BITS,
+        _ => false,
+    }
+}
+
+fn factorial_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, _| factorial_bits(n))
+}
+
+fn dfact_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, _| factorial_bits(n) / 2.0)
+}
+
+fn ncr_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, r| {
+        let r = r.min(n.saturating_sub(r));
+        factorial_bits(n) - factorial_bits(r) - factorial_bits(n - r)
+    })
+}
+
+fn npr_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, r| {
+        factorial_bits(n) - factorial_bits(n.saturating_sub(r))
+    })
+}
+
+fn catalan_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, _| 2.0 * n as f64)
+}
+
+fn fib_cost(args: &[Value]) -> bool {
+    too_many_bits(args, |n, _| n as f64 * 0.6943)
+}
+
+// 取非负的小整数参数
+fn count(arg: &Value) -> Result<u64, String> {
+    let n = arg.to_integer()?;
+    if n.is_negative() {
+        return Err("argument must be non-negative".to_string());
+    }
+    n.to_u64().ok_or_else(|| "argument too large".to_string())
+}
+
+// 用斯特林公式估算 n! 的位数，提前拒绝算不完的输入
+fn check_bits(bits: f64) -> Result<(), String> {
+    if bits > MAX_INT_BITS as f64 {
+        return Err("integer result too large".to_string());
+    }
+    Ok(())
+}
+
+fn factorial_bits(n: u64) -> f64 {
+    if n < 2 {
+        return 0.0;
+    }
+    let n = n as f64;
+    (n * n.ln() - n + 0.5 * (2.0 * std::f64::consts::PI * n).ln()) / std::f64::consts::LN_2
+}
+
+// [lo, hi] 内步长为 step 的连乘，二分递归让乘数规模接近
+fn product(lo: u64, hi: u64, step: u64) -> BigInt {
+    if lo > hi {
+        return BigInt::one();
+    }
+    let terms = (hi - lo) / step + 1;
+    if terms <= 16 {
+        return (0..terms).map(|i| BigInt::from(lo + i * step)).product();
+    }
+    let mid = lo + (terms / 2) * step;
+    product(lo, mid - step, step) * product(mid, hi, step)
+}
+
+pub fn factorial(n: u64) -> Result<BigInt, String> {
+    check_bits(factorial_bits(n))?;
+    Ok(product(2, n, 1))
+}
+
+pub fn binomial(n: u64, r: u64) -> Result<BigInt, String> {
+    if r > n {
+        return Ok(BigInt::zero());
+    }
+    let r = r.min(n - r);
+    check_bits(factorial_bits(n) - factorial_bits(r) - factorial_bits(n - r))?;
+    if r > 1000 && n <= SIEVE_LIMIT {
+        return Ok(binomial_by_primes(n, r));
+    }
+    // n 很大时 r 一定很小（结果位数有上限），直接相除
+    Ok(product(n - r + 1, n, 1) / product(2, r, 1))
+}
+
+// r 较大时在这个范围内筛素数，按素因子的指数算组合数，大数除法是平方级的
+const SIEVE_LIMIT: u64 = 1 << 23;
+
+// Legendre 公式：p 在 C(n, r) 里的指数是 Σ ⌊n/p^k⌋ - ⌊r/p^k⌋ - ⌊(n-r)/p^k⌋
+fn binomial_by_primes(n: u64, r: u64) -> BigInt {
+    let mut composite = vec![false; n as usize + 1];
+    let mut powers = Vec::new();
+    for p in 2..=n {
+        if composite[p as usize] {
+            continue;
+        }
+        for multiple in (p * p..=n).step_by(p as usize) {
+            composite[multiple as usize] = true;
+        }
+        let mut exponent = 0;
+        let mut power = p;
+        while power <= n {
+            exponent += n / power - r / power - (n - r) / power;
+            power = match power.checked_mul(p) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        if exponent > 0 {
+            powers.push(num::pow(BigInt::from(p), exponent as usize));
+        }
+    }
+    product_of(&powers)
+}
+
+// 一组大整数的二分连乘
+fn product_of(values: &[BigInt]) -> BigInt {
+    if values.len() <= 16 {
+        return values.iter().product();
+    }
+    let (left, right) = values.split_at(values.len() / 2);
+    product_of(left) * product_of(right)
+}
+
+fn factorial_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Int(factorial(count(&args[0])?)?))
+}
+
+// 双阶乘 n!! = n·(n-2)·(n-4)…，约定 0!! = (-1)!! = 1
+fn dfact_fn(args: &[Value]) -> FnResult {
+    let n = args[0].to_integer()?;
+    if n == BigInt::from(-1) {
+        return Ok(Value::Int(BigInt::one()));
+    }
+    let n = count(&args[0])?;
+    check_bits(factorial_bits(n) / 2.0)?;
+    let start = if n % 2 == 0 { 2 } else { 1 };
+    Ok(Value::Int(product(start, n, 2)))
+}
+
+fn ncr_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Int(binomial(count(&args[0])?, count(&args[1])?)?))
+}
+
+fn npr_fn(args: &[Value]) -> FnResult {
+    let n = count(&args[0])?;
+    let r = count(&args[1])?;
+    if r > n {
+        return Ok(Value::Int(BigInt::zero()));
+    }
+    check_bits(factorial_bits(n) - factorial_bits(n - r))?;
+    Ok(Value::Int(product(n - r + 1, n, 1)))
+}
+
+// C_n = C(2n, n) / (n + 1)
+fn catalan_fn(args: &[Value]) -> FnResult {
+    let n = count(&args[0])?;
+    let twice = n
+        .checked_mul(2)
+        .ok_or_else(|| "argument too large".to_string())?;
+    let central = binomial(twice, n)?;
+    Ok(Value::Int(central / (n + 1)))
+}
+
+// 快速倍增：F(2k) = F(k)(2F(k+1) - F(k))，F(2k+1) = F(k)^2 + F(k+1)^2
+fn fib_fn(args: &[Value]) -> FnResult {
+    let n = count(&args[0])?;
+    check_bits(n as f64 * 0.6943)?;
+    let mut a = BigInt::zero();
+    let mut b = BigInt::one();
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        let c = &a * (&b * 2 - &a);
+        let d = &a * &a + &b * &b;
+        if (n >> bit) & 1 == 0 {
+            a = c;
+            b = d;
+        } else {
+            b = &c + &d;
+            a = d;
+        }
+    }
+    Ok(Value::Int(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binomial_by_primes_matches_division() {
+        for n in [0, 1, 2, 10, 97, 100, 1024, 5000] {
+            for r in [0, 1, 2, n / 3, n / 2, n].into_iter().filter(|r| *r <= n) {
+                let expected = product(n - r + 1, n, 1) / product(2, r, 1);
+                assert_eq!(binomial_by_primes(n, r), expected, "C({}, {})", n, r);
+            }
+        }
+    }
+}
@@ -0,0 +1,431 @@
+// 概率分布：pdf（离散分布为概率质量）、cdf 和分位数
+use std::f64::consts::PI;
+
+use super::special::{beta_inc, gamma_p, gamma_q, ln_gamma};
+use super::{Arity, Builtin, FnResult};
+use crate::engine::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    Normal { mean: f64, sd: f64 },
+    Binomial { n: u64, p: f64 },
+    Poisson { lambda: f64 },
+    Uniform { a: f64, b: f64 },
+    Exponential { rate: f64 },
+    StudentT { df: f64 },
+    ChiSquared { df: f64 },
+}
+
+impl Distribution {
+    // 每种分布的默认参数，界面上切换分布时使用
+    pub const ALL: [Distribution; 7] = [
+        Distribution::Normal { mean: 0.0, sd: 1.0 },
+        Distribution::Binomial { n: 10, p: 0.5 },
+        Distribution::Poisson { lambda: 1.0 },
+        Distribution::Uniform { a: 0.0, b: 1.0 },
+        Distribution::Exponential { rate: 1.0 },
+        Distribution::StudentT { df: 1.0 },
+        Distribution::ChiSquared { df: 1.0 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Normal { .. } => "Normal",
+            Distribution::Binomial { .. } => "Binomial",
+            Distribution::Poisson { .. } => "Poisson",
+            Distribution::Uniform { .. } => "Uniform",
+            Distribution::Exponential { .. } => "Exponential",
+            Distribution::StudentT { .. } => "Student's t",
+            Distribution::ChiSquared { .. } => "Chi-square",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let ok = match *self {
+            Distribution::Normal { sd, .. } => sd > 0.0,
+            Distribution::Binomial { p, .. } => (0.0..=1.0).contains(&p),
+            Distribution::Poisson { lambda } => lambda >= 0.0,
+            Distribution::Uniform { a, b } => a < b,
+            Distribution::Exponential { rate } => rate > 0.0,
+            Distribution::StudentT { df } | Distribution::ChiSquared { df } => df > 0.0,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!(
+                "invalid parameters for {} distribution",
+                self.name()
+            ))
+        }
+    }
+
+    pub fn is_discrete(&self) -> bool {
+        matches!(
+            self,
+            Distribution::Binomial { .. } | Distribution::Poisson { .. }
+        )
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        match *self {
+            Distribution::Normal { mean, sd } => {
+                let z = (x - mean) / sd;
+                (-0.5 * z * z).exp() / (sd * (2.0 * PI).sqrt())
+            }
+            Distribution::Binomial { n, p } => {
+                if x.fract() != 0.0 || x < 0.0 || x > n as f64 {
+                    return 0.0;
+                }
+                let n = n as f64;
+                if p == 0.0 || p == 1.0 {
+                    let certain = if p == 0.0 { 0.0 } else { n };
+                    return if x == certain { 1.0 } else { 0.0 };
+                }
+                (ln_choose(n, x) + x * p.ln() + (n - x) * (1.0 - p).ln()).exp()
+            }
+            Distribution::Poisson { lambda } => {
+                if x.fract() != 0.0 || x < 0.0 {
+                    return 0.0;
+                }
+                if lambda == 0.0 {
+                    return if x == 0.0 { 1.0 } else { 0.0 };
+                }
+                (x * lambda.ln() - lambda - ln_gamma(x + 1.0)).exp()
+            }
+            Distribution::Uniform { a, b } => {
+                if (a..=b).contains(&x) {
+                    1.0 / (b - a)
+                } else {
+                    0.0
+                }
+            }
+            Distribution::Exponential { rate } => {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    rate * (-rate * x).exp()
+                }
+            }
+            Distribution::StudentT { df } => {
+                let ln_norm =
+                    ln_gamma((df + 1.0) / 2.0) - ln_gamma(df / 2.0) - 0.5 * (df * PI).ln();
+                (ln_norm - (df + 1.0) / 2.0 * (1.0 + x * x / df).ln()).exp()
+            }
+            Distribution::ChiSquared { df } => {
+                if x < 0.0 {
+                    return 0.0;
+                }
+                if x == 0.0 {
+                    return match df.partial_cmp(&2.0) {
+                        Some(std::cmp::Ordering::Less) => f64::INFINITY,
+                        Some(std::cmp::Ordering::Equal) => 0.5,
+                        _ => 0.0,
+                    };
+                }
+                let k = df / 2.0;
+                ((k - 1.0) * x.ln() - x / 2.0 - k * 2f64.ln() - ln_gamma(k)).exp()
+            }
+        }
+    }
+
+    pub fn cdf(&self, x: f64) -> f64 {
+        match *self {
+            Distribution::Normal { mean, sd } => standard_normal_cdf((x - mean) / sd),
+            Distribution::Binomial { n, p } => {
+                let k = x.floor();
+                if k < 0.0 {
+                    0.0
+                } else if k >= n as f64 {
+                    1.0
+                } else {
+                    beta_inc(n as f64 - k, k + 1.0, 1.0 - p)
+                }
+            }
+            Distribution::Poisson { lambda } => {
+                let k = x.floor();
+                if k < 0.0 {
+                    0.0
+                } else if lambda == 0.0 {
+                    1.0
+                } else {
+                    gamma_q(k + 1.0, lambda)
+                }
+            }
+            Distribution::Uniform { a, b } => ((x - a) / (b - a)).clamp(0.0, 1.0),
+            Distribution::Exponential { rate } => {
+                if x < 0.0 {
+                    0.0
+                } else {
+                    1.0 - (-rate * x).exp()
+                }
+            }
+            Distribution::StudentT { df } => {
+                let tail = 0.5 * beta_inc(df / 2.0, 0.5, df / (df + x * x));
+                if x < 0.0 {
+                    tail
+                } else {
+                    1.0 - tail
+                }
+            }
+            Distribution::ChiSquared { df } => gamma_p(df / 2.0, x / 2.0),
+        }
+    }
+
+    // 离散分布返回满足 cdf(k) >= p 的最小 k
+    pub fn quantile(&self, p: f64) -> f64 {
+        if !(0.0..=1.0).contains(&p) {
+            return f64::NAN;
+        }
+        match *self {
+            Distribution::Normal { mean, sd } => mean + sd * standard_normal_quantile(p),
+            Distribution::Uniform { a, b } => a + p * (b - a),
+            Distribution::Exponential { rate } => -(1.0 - p).ln() / rate,
+            Distribution::Binomial { n, .. } => self.discrete_quantile(p, n as f64),
+            Distribution::Poisson { .. } => {
+                if p == 1.0 {
+                    return f64::INFINITY;
+                }
+                let mut hi = 1.0;
+                while self.cdf(hi) < p && hi < 1e15 {
+                    hi *= 2.0;
+                }
+                self.discrete_quantile(p, hi)
+            }
+            Distribution::StudentT { .. } => {
+                if p == 0.0 || p == 1.0 {
+                    return if p == 0.0 {
+                        f64::NEG_INFINITY
+                    } else {
+                        f64::INFINITY
+                    };
+                }
+                let mut width = 1.0;
+                while self.cdf(-width) > p || self.cdf(width) < p {
+                    width *= 2.0;
+                }
+                self.bisect(p, -width, width)
+            }
+            Distribution::ChiSquared { .. } => {
+                if p == 1.0 {
+                    return f64::INFINITY;
+                }
+                let mut hi = 1.0;
+                while self.cdf(hi) < p {
+                    hi *= 2.0;
+                }
+                self.bisect(p, 0.0, hi)
+            }
+        }
+    }
+
+    fn discrete_quantile(&self, p: f64, hi: f64) -> f64 {
+        let (mut lo, mut hi) = (0.0, hi);
+        while lo < hi {
+            let mid = ((lo + hi) / 2.0).floor();
+            let cdf = self.cdf(mid);
+            // 累积概率算不出来时分位数也没有意义
+            if cdf.is_nan() {
+                return f64::NAN;
+            }
+            if cdf >= p {
+                hi = mid;
+            } else {
+                lo = mid + 1.0;
+            }
+        }
+        lo
+    }
+
+    // 连续分布的 cdf 单调，二分求逆
+    fn bisect(&self, p: f64, mut lo: f64, mut hi: f64) -> f64 {
+        for _ in 0..200 {
+            let mid = (lo + hi) / 2.0;
+            let cdf = self.cdf(mid);
+            if cdf.is_nan() {
+                return f64::NAN;
+            }
+            if cdf < p {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+            if hi - lo <= 1e-14 * mid.abs().max(1.0) {
+                break;
+            }
+        }
+        (lo + hi) / 2.0
+    }
+}
+
+fn ln_choose(n: f64, k: f64) -> f64 {
+    ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0)
+}
+
+// Φ(z) = erfc(-z/√2) / 2，用 Q(1/2, z²/2) 保证尾部精度
+fn standard_normal_cdf(z: f64) -> f64 {
+    let tail = 0.5 * gamma_q(0.5, z * z / 2.0);
+    if z < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+// Acklam 的有理近似，再做一步 Halley 迭代修正
+fn standard_normal_quantile(p: f64) -> f64 {
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let x = if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    };
+    let e = standard_normal_cdf(x) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x - u / (1.0 + x * u / 2.0)
+}
+
+// 前缀 + pdf/cdf/inv 三个函数，第一个参数是 x（或分位数的 p），后面是分布参数
+macro_rules! distribution_functions {
+    ($($prefix:literal, $arity:expr, $build:expr;)*) => {
+        pub const FUNCTIONS: &[Builtin] = &[
+            $(
+                Builtin::new(concat!($prefix, "pdf"), $arity, |args| {
+                    apply(args, $build, Distribution::pdf)
+                }),
+                Builtin::new(concat!($prefix, "cdf"), $arity, |args| {
+                    apply(args, $build, Distribution::cdf)
+                }),
+                Builtin::new(concat!($prefix, "inv"), $arity, |args| {
+                    apply_quantile(args, $build)
+                }),
+            )*
+        ];
+    };
+}
+
+distribution_functions! {
+    "norm", Arity::Range(1, 3), normal;
+    "binom", Arity::Exact(3), binomial;
+    "poiss", Arity::Exact(2), poisson;
+    "unif", Arity::Range(1, 3), uniform;
+    "exp", Arity::Range(1, 2), exponential;
+    "t", Arity::Exact(2), student_t;
+    "chi2", Arity::Exact(2), chi_squared;
+}
+
+fn apply(
+    args: &[Value],
+    build: fn(&[Value]) -> Result<Distribution, String>,
+    f: fn(&Distribution, f64) -> f64,
+) -> FnResult {
+    let dist = build(&args[1..])?;
+    dist.validate()?;
+    Ok(Value::Float(f(&dist, args[0].to_f64())))
+}
+
+// 离散分布的分位数是整数
+fn apply_quantile(args: &[Value], build: fn(&[Value]) -> Result<Distribution, String>) -> FnResult {
+    let dist = build(&args[1..])?;
+    dist.validate()?;
+    let result = dist.quantile(args[0].to_f64());
+    if dist.is_discrete() && result.is_finite() {
+        return Ok(Value::Int((result as i64).into()));
+    }
+    Ok(Value::Float(result))
+}
+
+fn param(params: &[Value], i: usize, default: f64) -> f64 {
+    params.get(i).map_or(default, Value::to_f64)
+}
+
+fn normal(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::Normal {
+        mean: param(params, 0, 0.0),
+        sd: param(params, 1, 1.0),
+    })
+}
+
+fn binomial(params: &[Value]) -> Result<Distribution, String> {
+    let n = params[0].to_integer()?;
+    let n = u64::try_from(n).map_err(|_| "n must be a non-negative integer".to_string())?;
+    Ok(Distribution::Binomial {
+        n,
+        p: params[1].to_f64(),
+    })
+}
+
+fn poisson(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::Poisson {
+        lambda: params[0].to_f64(),
+    })
+}
+
+fn uniform(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::Uniform {
+        a: param(params, 0, 0.0),
+        b: param(params, 1, 1.0),
+    })
+}
+
+fn exponential(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::Exponential {
+        rate: param(params, 0, 1.0),
+    })
+}
+
+fn student_t(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::StudentT {
+        df: params[0].to_f64(),
+    })
+}
+
+fn chi_squared(params: &[Value]) -> Result<Distribution, String> {
+    Ok(Distribution::ChiSquared {
+        df: params[0].to_f64(),
+    })
+}
@@ -0,0 +1,104 @@
+// 内置函数的签名和一句话说明，用于自动补全；函数名取签名里括号前的部分
+// 方括号里的参数可以省略
+pub const DOCS: &[(&str, &str)] = &[
+    ("sqrt(x)", "Square root"),
+    ("exp(x)", "e raised to the power x"),
+    ("ln(x)", "Natural logarithm"),
+    ("sin(x)", "Sine (radians)"),
+    ("cos(x)", "Cosine (radians)"),
+    ("tan(x)", "Tangent (radians)"),
+    ("asin(x)", "Inverse sine"),
+    ("acos(x)", "Inverse cosine"),
+    ("atan(x)", "Inverse tangent"),
+    ("atan2(y, x)", "Angle of the point (x, y)"),
+    ("sinh(x)", "Hyperbolic sine"),
+    ("cosh(x)", "Hyperbolic cosine"),
+    ("tanh(x)", "Hyperbolic tangent"),
+    ("asinh(x)", "Inverse hyperbolic sine"),
+    ("acosh(x)", "Inverse hyperbolic cosine"),
+    ("atanh(x)", "Inverse hyperbolic tangent"),
+    ("abs(x)", "Absolute value"),
+    ("floor(x)", "Round down to an integer"),
+    ("ceil(x)", "Round up to an integer"),
+    ("round(x)", "Round to the nearest integer"),
+    ("signum(x)", "Sign of x: -1, 0 or 1"),
+    ("max(a, b, ...)", "Largest argument"),
+    ("min(a, b, ...)", "Smallest argument"),
+    ("gcd(a, b, ...)", "Greatest common divisor"),
+    ("lcm(a, b, ...)", "Least common multiple"),
+    ("is_prime(n)", "Whether n is prime"),
+    ("next_prime(n)", "Smallest prime greater than n"),
+    ("factor(n)", "Prime factorization"),
+    ("totient(n)", "Euler's totient function"),
+    ("powmod(a, b, m)", "a^b modulo m"),
+    ("modinv(a, m)", "Inverse of a modulo m"),
+    ("factorial(n)", "n! (also written n!)"),
+    ("dfact(n)", "Double factorial n!!"),
+    ("nCr(n, r)", "Number of combinations"),
+    ("nPr(n, r)", "Number of permutations"),
+    ("catalan(n)", "n-th Catalan number"),
+    ("fib(n)", "n-th Fibonacci number"),
+    ("normpdf(x, [mean, sd])", "Normal density"),
+    ("normcdf(x, [mean, sd])", "Normal cumulative probability"),
+    ("norminv(p, [mean, sd])", "Normal quantile"),
+    ("binompdf(k, n, p)", "Binomial probability P(X = k)"),
+    ("binomcdf(k, n, p)", "Binomial cumulative probability"),
+    ("binominv(q, n, p)", "Binomial quantile"),
+    ("poisspdf(k, lambda)", "Poisson probability P(X = k)"),
+    ("poisscdf(k, lambda)", "Poisson cumulative probability"),
+    ("poissinv(p, lambda)", "Poisson quantile"),
+    ("unifpdf(x, [a, b])", "Uniform density"),
+    ("unifcdf(x, [a, b])", "Uniform cumulative probability"),
+    ("unifinv(p, [a, b])", "Uniform quantile"),
+    ("exppdf(x, [rate])", "Exponential density"),
+    ("expcdf(x, [rate])", "Exponential cumulative probability"),
+    ("expinv(p, [rate])", "Exponential quantile"),
+    ("tpdf(x, df)", "Student's t density"),
+    ("tcdf(x, df)", "Student's t cumulative probability"),
+    ("tinv(p, df)", "Student's t quantile"),
+    ("chi2pdf(x, df)", "Chi-squared density"),
+    ("chi2cdf(x, df)", "Chi-squared cumulative probability"),
+    ("chi2inv(p, df)", "Chi-squared quantile"),
+    ("rand()", "Uniform random number in [0, 1)"),
+    ("randint(a, b)", "Random integer between a and b inclusive"),
+    ("sample(list, k)", "k random items without replacement"),
+    ("seed(n)", "Reset the random number generator"),
+    ("fv(rate, nper, pmt, [pv, type])", "Future value"),
+    ("pv(rate, nper, pmt, [fv, type])", "Present value"),
+    ("pmt(rate, nper, pv, [fv, type])", "Payment per period"),
+    ("nper(rate, pmt, pv, [fv, type])", "Number of periods"),
+    (
+        "rate(nper, pmt, pv, [fv, type, guess])",
+        "Interest rate per period",
+    ),
+    (
+        "npv(rate, flows)",
+        "Net present value, first flow at t = 1 as in Excel",
+    ),
+    ("irr(flows, [guess])", "Internal rate of return"),
+    ("pct_change(old, new)", "Percentage change from old to new"),
+    (
+        "poly(expr, [x])",
+        "Polynomial from an expression or coefficients",
+    ),
+    ("deriv(p)", "Derivative of a polynomial"),
+    ("peval(p, x)", "Evaluate a polynomial at x"),
+    ("pdiv(p, q)", "Polynomial quotient and remainder"),
+    ("pgcd(p, q)", "Polynomial greatest common divisor"),
+    ("roots(p)", "All complex roots of a polynomial"),
+    ("polyfit(xs, ys, n)", "Least-squares polynomial of degree n"),
+    (
+        "integrate(expr, x, a, b)",
+        "Definite integral with error estimate",
+    ),
+    ("sum(expr, k, a, b)", "Sum over k from a to b"),
+    ("prod(expr, k, a, b)", "Product over k from a to b"),
+    (
+        "piecewise(c1, v1, ..., [default])",
+        "Value of the first true condition",
+    ),
+    (
+        "latex(expr)",
+        "LaTeX source of an expression, not evaluated",
+    ),
+];
@@ -0,0 +1,93 @@
+// 初等函数，覆盖原来 meval 支持的那一套
+use num::Signed;
+
+use super::{Arity, Builtin, FnResult};
+use crate::engine::Value;
+
+macro_rules! float_fn {
+    ($name:literal, $f:expr) => {
+        Builtin::new($name, Arity::Exact(1), |args| {
+            Ok(Value::Float($f(args[0].to_f64())))
+        })
+    };
+}
+
+pub const FUNCTIONS: &[Builtin] = &[
+    float_fn!("sqrt", f64::sqrt),
+    float_fn!("exp", f64::exp),
+    float_fn!("ln", f64::ln),
+    float_fn!("sin", f64::sin),
+    float_fn!("cos", f64::cos),
+    float_fn!("tan", f64::tan),
+    float_fn!("asin", f64::asin),
+    float_fn!("acos", f64::acos),
+    float_fn!("atan", f64::atan),
+    float_fn!("sinh", f64::sinh),
+    float_fn!("cosh", f64::cosh),
+    float_fn!("tanh", f64::tanh),
+    float_fn!("asinh", f64::asinh),
+    float_fn!("acosh", f64::acosh),
+    float_fn!("atanh", f64::atanh),
+    Builtin::new("abs", Arity::Exact(1), abs),
+    Builtin::new("floor", Arity::Exact(1), floor),
+    Builtin::new("ceil", Arity::Exact(1), ceil),
+    Builtin::new("round", Arity::Exact(1), round),
+    Builtin::new("signum", Arity::Exact(1), signum),
+    Builtin::new("max", Arity::AtLeast(1), max),
+    Builtin::new("min", Arity::AtLeast(1), min),
+    Builtin::new("atan2", Arity::Exact(2), atan2),
+];
+
+// 整数参数保持精确，浮点数和积分的估计值才走 f64
+fn int_or_float(
+    arg: &Value,
+    int: fn(&num::BigInt) -> num::BigInt,
+    float: fn(f64) -> f64,
+) -> FnResult {
+    match arg {
+        Value::Float(x) | Value::Estimate(x, _) => Ok(Value::Float(float(*x))),
+        other => Ok(Value::Int(int(&other.to_integer()?))),
+    }
+}
+
+fn abs(args: &[Value]) -> FnResult {
+    int_or_float(&args[0], |n| n.abs(), f64::abs)
+}
+
+fn floor(args: &[Value]) -> FnResult {
+    int_or_float(&args[0], Clone::clone, f64::floor)
+}
+
+fn ceil(args: &[Value]) -> FnResult {
+    int_or_float(&args[0], Clone::clone, f64::ceil)
+}
+
+fn round(args: &[Value]) -> FnResult {
+    int_or_float(&args[0], Clone::clone, f64::round)
+}
+
+fn signum(args: &[Value]) -> FnResult {
+    int_or_float(&args[0], |n| n.signum(), f64::signum)
+}
+
+fn extreme(args: &[Value], pick_rhs: fn(f64, f64) -> bool) -> FnResult {
+    let mut best = &args[0];
+    for arg in &args[1..] {
+        if pick_rhs(best.to_f64(), arg.to_f64()) {
+            best = arg;
+        }
+    }
+    Ok(best.clone())
+}
+
+fn max(args: &[Value]) -> FnResult {
+    extreme(args, |a, b| b > a)
+}
+
+fn min(args: &[Value]) -> FnResult {
+    extreme(args, |a, b| b < a)
+}
+
+fn atan2(args: &[Value]) -> FnResult {
+    Ok(Value::Float(args[0].to_f64().atan2(args[1].to_f64())))
+}
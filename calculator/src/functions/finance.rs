@@ -0,0 +1,301 @@
+// 金融函数，参数顺序和符号约定与 Excel 相同：付出的钱为负，收到的钱为正
+// type = 0 表示期末付款，type = 1 表示期初付款
+use super::{Arity, Builtin, FnResult};
+use crate::engine::Value;
+
+pub const FUNCTIONS: &[Builtin] = &[
+    Builtin::new("fv", Arity::Range(3, 5), fv_fn),
+    Builtin::new("pv", Arity::Range(3, 5), pv_fn),
+    Builtin::new("pmt", Arity::Range(3, 5), pmt_fn),
+    Builtin::new("nper", Arity::Range(3, 5), nper_fn),
+    Builtin::new("rate", Arity::Range(3, 6), rate_fn),
+    Builtin::new("npv", Arity::Exact(2), npv_fn),
+    Builtin::new("irr", Arity::Range(1, 2), irr_fn),
+    Builtin::new("pct_change", Arity::Exact(2), pct_change_fn),
+];
+
+const MAX_ITER: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+fn arg(args: &[Value], i: usize, default: f64) -> f64 {
+    args.get(i).map_or(default, Value::to_f64)
+}
+
+// (1 + r)^n 以及年金系数 ((1 + r)^n - 1) / r，r = 0 时取极限 n
+fn growth(rate: f64, nper: f64) -> (f64, f64) {
+    if rate == 0.0 {
+        (1.0, nper)
+    } else {
+        let g = (1.0 + rate).powf(nper);
+        (g, (g - 1.0) / rate)
+    }
+}
+
+pub fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, due: f64) -> f64 {
+    let (g, annuity) = growth(rate, nper);
+    -(pv * g + pmt * (1.0 + rate * due) * annuity)
+}
+
+pub fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, due: f64) -> f64 {
+    let (g, annuity) = growth(rate, nper);
+    -(fv + pmt * (1.0 + rate * due) * annuity) / g
+}
+
+pub fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, due: f64) -> f64 {
+    let (g, annuity) = growth(rate, nper);
+    -(pv * g + fv) / ((1.0 + rate * due) * annuity)
+}
+
+pub fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, due: f64) -> f64 {
+    if rate == 0.0 {
+        return -(pv + fv) / pmt;
+    }
+    let adjusted = pmt * (1.0 + rate * due);
+    ((adjusted - fv * rate) / (adjusted + pv * rate)).ln() / (1.0 + rate).ln()
+}
+
+// 牛顿迭代，导数用中心差分
+fn newton(f: impl Fn(f64) -> f64, guess: f64) -> Option<f64> {
+    let mut x = guess;
+    for _ in 0..MAX_ITER {
+        let y = f(x);
+        let h = 1e-6 * x.abs().max(1e-3);
+        let slope = (f(x + h) - f(x - h)) / (2.0 * h);
+        if slope == 0.0 || !slope.is_finite() {
+            return None;
+        }
+        let next = x - y / slope;
+        if !next.is_finite() || next <= -1.0 {
+            return None;
+        }
+        if (next - x).abs() < TOLERANCE * next.abs().max(1.0) {
+            return Some(next);
+        }
+        x = next;
+    }
+    None
+}
+
+pub fn rate(nper: f64, pmt: f64, pv: f64, fv: f64, due: f64, guess: f64) -> Option<f64> {
+    newton(|r| self::fv(r, nper, pmt, pv, due) - fv, guess)
+}
+
+// 第一项发生在第 0 期（不折现），IRR 就是让它为 0 的利率
+fn discounted(rate: f64, flows: &[f64]) -> f64 {
+    flows
+        .iter()
+        .enumerate()
+        .map(|(t, cf)| cf / (1.0 + rate).powi(t as i32))
+        .sum()
+}
+
+// 和 Excel 的 NPV 一样，第一项发生在第 1 期末
+pub fn npv(rate: f64, flows: &[f64]) -> f64 {
+    discounted(rate, flows) / (1.0 + rate)
+}
+
+// 先试牛顿迭代，不收敛时在 (-1, 10] 内二分
+pub fn irr(flows: &[f64], guess: f64) -> Option<f64> {
+    if let Some(r) = newton(|r| discounted(r, flows), guess) {
+        return Some(r);
+    }
+    let (mut lo, mut hi) = (-0.999_999, 10.0);
+    if discounted(lo, flows).signum() == discounted(hi, flows).signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.0;
+        if discounted(mid, flows).signum() == discounted(lo, flows).signum() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some((lo + hi) / 2.0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmortizationRow {
+    pub period: u32,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub balance: f64,
+}
+
+// 等额本息还款计划，rate 为每期利率
+pub fn amortization_schedule(principal: f64, rate: f64, periods: u32) -> Vec<AmortizationRow> {
+    let payment = -pmt(rate, periods as f64, principal, 0.0, 0.0);
+    let mut balance = principal;
+    let mut rows = Vec::with_capacity(periods as usize);
+    for period in 1..=periods {
+        let interest = balance * rate;
+        let mut principal_part = payment - interest;
+        // 最后一期把舍入误差一起还清
+        if period == periods {
+            principal_part = balance;
+        }
+        balance -= principal_part;
+        rows.push(AmortizationRow {
+            period,
+            payment: principal_part + interest,
+            interest,
+            principal: principal_part,
+            balance,
+        });
+    }
+    rows
+}
+
+pub fn schedule_to_csv(rows: &[AmortizationRow]) -> String {
+    let mut csv = String::from("period,payment,interest,principal,balance\n");
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{:.2},{:.2},{:.2},{:.2}\n",
+            row.period, row.payment, row.interest, row.principal, row.balance
+        ));
+    }
+    csv
+}
+
+fn fv_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(fv(
+        arg(args, 0, 0.0),
+        arg(args, 1, 0.0),
+        arg(args, 2, 0.0),
+        arg(args, 3, 0.0),
+        arg(args, 4, 0.0),
+    )))
+}
+
+fn pv_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(pv(
+        arg(args, 0, 0.0),
+        arg(args, 1, 0.0),
+        arg(args, 2, 0.0),
+        arg(args, 3, 0.0),
+        arg(args, 4, 0.0),
+    )))
+}
+
+fn pmt_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(pmt(
+        arg(args, 0, 0.0),
+        arg(args, 1, 0.0),
+        arg(args, 2, 0.0),
+        arg(args, 3, 0.0),
+        arg(args, 4, 0.0),
+    )))
+}
+
+fn nper_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(nper(
+        arg(args, 0, 0.0),
+        arg(args, 1, 0.0),
+        arg(args, 2, 0.0),
+        arg(args, 3, 0.0),
+        arg(args, 4, 0.0),
+    )))
+}
+
+fn rate_fn(args: &[Value]) -> FnResult {
+    rate(
+        arg(args, 0, 0.0),
+        arg(args, 1, 0.0),
+        arg(args, 2, 0.0),
+        arg(args, 3, 0.0),
+        arg(args, 4, 0.0),
+        arg(args, 5, 0.1),
+    )
+    .map(Value::Float)
+    .ok_or_else(|| "did not converge".to_string())
+}
+
+fn cash_flows(value: &Value) -> Result<Vec<f64>, String> {
+    let flows: Vec<f64> = value.as_list()?.iter().map(Value::to_f64).collect();
+    if flows.is_empty() {
+        return Err("cash flow list is empty".to_string());
+    }
+    Ok(flows)
+}
+
+fn npv_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(npv(args[0].to_f64(), &cash_flows(&args[1])?)))
+}
+
+fn irr_fn(args: &[Value]) -> FnResult {
+    let flows = cash_flows(&args[0])?;
+    // 没有一正一负的现金流时 IRR 不存在
+    if !(flows.iter().any(|&cf| cf > 0.0) && flows.iter().any(|&cf| cf < 0.0)) {
+        return Err("cash flows must include both positive and negative values".to_string());
+    }
+    irr(&flows, arg(args, 1, 0.1))
+        .map(Value::Float)
+        .ok_or_else(|| "did not converge".to_string())
+}
+
+// 从 old 变到 new 的百分比变化，pct_change(80, 100) = 25
+fn pct_change_fn(args: &[Value]) -> FnResult {
+    let (old, new) = (args[0].to_f64(), args[1].to_f64());
+    if old == 0.0 {
+        return Err("old value is zero".to_string());
+    }
+    Ok(Value::Float((new - old) / old.abs() * 100.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0)
+    }
+
+    // 参考值是 Excel 帮助里的示例
+    #[test]
+    fn matches_excel_examples() {
+        let cases = [
+            (
+                pmt(0.08 / 12.0, 10.0, 10000.0, 0.0, 0.0),
+                -1037.0320893591636,
+            ),
+            (
+                fv(0.06 / 12.0, 10.0, -200.0, -500.0, 1.0),
+                2581.4033740601362,
+            ),
+            (pv(0.08 / 12.0, 240.0, 500.0, 0.0, 0.0), -59777.14585118777),
+            (nper(0.01, -100.0, -1000.0, 10000.0, 1.0), 59.67386567429457),
+            (
+                rate(48.0, -200.0, 8000.0, 0.0, 0.0, 0.1).unwrap(),
+                0.007701472488201705,
+            ),
+            // NPV 的第一项在第 1 期末贴现
+            (
+                npv(0.1, &[-10000.0, 3000.0, 4200.0, 6800.0]),
+                1188.4434123352216,
+            ),
+            (
+                irr(
+                    &[-70000.0, 12000.0, 15000.0, 18000.0, 21000.0, 26000.0],
+                    0.1,
+                )
+                .unwrap(),
+                0.0866309480365316,
+            ),
+        ];
+        for (i, (actual, expected)) in cases.into_iter().enumerate() {
+            assert!(close(actual, expected), "case {}: {}", i, actual);
+        }
+    }
+
+    #[test]
+    fn schedule_pays_off_the_loan() {
+        let rows = amortization_schedule(10000.0, 0.08 / 12.0, 10);
+        assert_eq!(rows.len(), 10);
+        assert!(close(rows[0].payment, 1037.0320893591636));
+        assert!(close(rows[0].interest, 10000.0 * 0.08 / 12.0));
+        let repaid: f64 = rows.iter().map(|row| row.principal).sum();
+        assert!(close(repaid, 10000.0));
+        assert_eq!(rows[9].balance, 0.0);
+    }
+}
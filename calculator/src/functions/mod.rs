@@ -0,0 +1,131 @@
+// 内置函数表，每个子模块导出自己的 FUNCTIONS
+mod calculus;
+mod combinatorics;
+mod distributions;
+mod docs;
+mod elementary;
+mod finance;
+mod number_theory;
+mod polynomial;
+mod random;
+mod regression;
+mod special;
+
+pub use calculus::integrate;
+pub use distributions::Distribution;
+pub use docs::DOCS;
+pub use finance::{amortization_schedule, schedule_to_csv, AmortizationRow};
+pub use polynomial::Polynomial;
+pub use regression::{fit, Fit, Model, MAX_DEGREE};
+
+use std::fmt::{Display, Formatter};
+
+use crate::engine::{Context, Value};
+
+pub type FnResult = Result<Value, String>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Arity {
+    Exact(usize),
+    Range(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, n: usize) -> bool {
+        match *self {
+            Arity::Exact(k) => n == k,
+            Arity::Range(lo, hi) => (lo..=hi).contains(&n),
+            Arity::AtLeast(k) => n >= k,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(k) => write!(f, "{}", k),
+            Arity::Range(lo, hi) => write!(f, "{} to {}", lo, hi),
+            Arity::AtLeast(k) => write!(f, "at least {}", k),
+        }
+    }
+}
+
+pub enum Function {
+    Pure(fn(&[Value]) -> FnResult),
+    // 需要访问会话状态（比如随机数发生器）的函数
+    Session(fn(&mut Context, &[Value]) -> FnResult),
+}
+
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub func: Function,
+    // 参数很大时可能要算很久：实时预览先用它估计开销，太大时不调用
+    pub slow: Option<fn(&[Value]) -> bool>,
+}
+
+impl Builtin {
+    pub const fn new(name: &'static str, arity: Arity, func: fn(&[Value]) -> FnResult) -> Self {
+        Builtin {
+            name,
+            arity,
+            func: Function::Pure(func),
+            slow: None,
+        }
+    }
+
+    pub const fn session(
+        name: &'static str,
+        arity: Arity,
+        func: fn(&mut Context, &[Value]) -> FnResult,
+    ) -> Self {
+        Builtin {
+            name,
+            arity,
+            func: Function::Session(func),
+            slow: None,
+        }
+    }
+
+    pub const fn slow(mut self, too_slow: fn(&[Value]) -> bool) -> Self {
+        self.slow = Some(too_slow);
+        self
+    }
+}
+
+// 预览时精确整数结果超过这么多位就不算了，调试版大约要几十毫秒
+pub const PREVIEW_BITS: f64 = 65536.0;
+
+// 通用的估计，factor、next_prime 之类的开销随参数增长得很快
+pub fn large_arguments(args: &[Value]) -> bool {
+    args.iter().any(large)
+}
+
+fn large(value: &Value) -> bool {
+    match value {
+        Value::Int(n) => n.bits() > 64,
+        Value::Float(x) | Value::Estimate(x, _) => x.abs() >= 2f64.powi(64),
+        Value::Poly(p) => p.degree() > 20,
+        Value::List(items) => items.iter().any(large),
+        _ => false,
+    }
+}
+
+const TABLES: &[&[Builtin]] = &[
+    elementary::FUNCTIONS,
+    number_theory::FUNCTIONS,
+    combinatorics::FUNCTIONS,
+    distributions::FUNCTIONS,
+    random::FUNCTIONS,
+    finance::FUNCTIONS,
+    polynomial::FUNCTIONS,
+    regression::FUNCTIONS,
+];
+
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    TABLES
+        .iter()
+        .flat_map(|table| table.iter())
+        .find(|b| b.name == name)
+}
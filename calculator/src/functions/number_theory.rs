@@ -0,0 +1,265 @@
+// 数论函数，全部基于 num 的任意精度整数
+use num::integer::Integer;
+use num::{BigInt, One, Signed, Zero};
+
+use super::{large_arguments, Arity, Builtin, FnResult};
+use crate::engine::Value;
+
+pub const FUNCTIONS: &[Builtin] = &[
+    Builtin::new("gcd", Arity::AtLeast(1), gcd_fn),
+    Builtin::new("lcm", Arity::AtLeast(1), lcm_fn),
+    Builtin::new("is_prime", Arity::Exact(1), is_prime_fn).slow(is_prime_cost),
+    Builtin::new("next_prime", Arity::Exact(1), next_prime_fn).slow(large_arguments),
+    Builtin::new("factor", Arity::Exact(1), factor_fn).slow(large_arguments),
+    Builtin::new("totient", Arity::Exact(1), totient_fn).slow(large_arguments),
+    Builtin::new("powmod", Arity::Exact(3), powmod_fn).slow(powmod_cost),
+    Builtin::new("modinv", Arity::Exact(2), modinv_fn),
+];
+
+// Miller-Rabin 每个底数一次模幂，开销是位数的三次方
+fn is_prime_cost(args: &[Value]) -> bool {
+    args[0].to_integer().is_ok_and(|n| n.bits() > 512)
+}
+
+// 模幂的开销约为 指数位数 × 模数位数的平方
+fn powmod_cost(args: &[Value]) -> bool {
+    let Ok(nums) = integers(args) else {
+        return false;
+    };
+    let m = nums[2].bits() as f64;
+    nums[1].bits() as f64 * m * m > 1e9
+}
+
+// 试除用的小素数上限
+const TRIAL_LIMIT: u32 = 1000;
+// Pollard rho 单轮最多迭代次数，超过就放弃，避免界面卡死
+const RHO_BUDGET: usize = 1 << 20;
+
+fn integers(args: &[Value]) -> Result<Vec<BigInt>, String> {
+    args.iter().map(Value::to_integer).collect()
+}
+
+fn gcd_fn(args: &[Value]) -> FnResult {
+    let nums = integers(args)?;
+    Ok(Value::Int(
+        nums.iter().fold(BigInt::zero(), |acc, n| acc.gcd(n)),
+    ))
+}
+
+fn lcm_fn(args: &[Value]) -> FnResult {
+    let nums = integers(args)?;
+    Ok(Value::Int(
+        nums.iter().fold(BigInt::one(), |acc, n| acc.lcm(n)),
+    ))
+}
+
+fn is_prime_fn(args: &[Value]) -> FnResult {
+    let n = args[0].to_integer()?;
+    Ok(Value::Bool(is_prime(&n)))
+}
+
+fn next_prime_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Int(next_prime(&args[0].to_integer()?)))
+}
+
+fn factor_fn(args: &[Value]) -> FnResult {
+    let n = args[0].to_integer()?;
+    if n.is_zero() {
+        return Err("cannot factor 0".to_string());
+    }
+    let mut factors = factorize(&n.abs())?;
+    if n.is_negative() {
+        factors.insert(0, (BigInt::from(-1), 1));
+    }
+    Ok(Value::Factors(factors))
+}
+
+fn totient_fn(args: &[Value]) -> FnResult {
+    let n = args[0].to_integer()?;
+    if !n.is_positive() {
+        return Err("argument must be positive".to_string());
+    }
+    Ok(Value::Int(totient(&n)?))
+}
+
+fn powmod_fn(args: &[Value]) -> FnResult {
+    let nums = integers(args)?;
+    Ok(Value::Int(powmod(&nums[0], &nums[1], &nums[2])?))
+}
+
+fn modinv_fn(args: &[Value]) -> FnResult {
+    let nums = integers(args)?;
+    Ok(Value::Int(modinv(&nums[0], &nums[1])?))
+}
+
+// Miller-Rabin，前 13 个素数作底数在 3.3e24 以内是确定性的
+pub fn is_prime(n: &BigInt) -> bool {
+    if *n < BigInt::from(2) {
+        return false;
+    }
+    const BASES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+    for p in BASES {
+        let p = BigInt::from(p);
+        if *n == p {
+            return true;
+        }
+        if (n % &p).is_zero() {
+            return false;
+        }
+    }
+    let n_minus_1: BigInt = n - 1;
+    let s = n_minus_1.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_1 >> s;
+    'witness: for a in BASES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == n_minus_1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = x.modpow(&BigInt::from(2), n);
+            if x == n_minus_1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+pub fn next_prime(n: &BigInt) -> BigInt {
+    let two = BigInt::from(2);
+    if *n < two {
+        return two;
+    }
+    let mut candidate: BigInt = n + 1;
+    if candidate.is_even() && candidate != two {
+        candidate += 1;
+    }
+    while !is_prime(&candidate) {
+        candidate += 2;
+    }
+    candidate
+}
+
+// 返回按素数升序排列的 (p, k)，n 必须为正
+pub fn factorize(n: &BigInt) -> Result<Vec<(BigInt, u32)>, String> {
+    let mut primes = Vec::new();
+    let mut rest = n.clone();
+    let mut p = 2u32;
+    while p <= TRIAL_LIMIT && !rest.is_one() {
+        let bp = BigInt::from(p);
+        while (&rest % &bp).is_zero() {
+            rest /= &bp;
+            primes.push(bp.clone());
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+    split(&rest, &mut primes)?;
+    primes.sort();
+    let mut factors: Vec<(BigInt, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, k)) if *q == p => *k += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    Ok(factors)
+}
+
+// 递归地把合数拆开，直到全是素数
+fn split(n: &BigInt, primes: &mut Vec<BigInt>) -> Result<(), String> {
+    if n.is_one() {
+        return Ok(());
+    }
+    if is_prime(n) {
+        primes.push(n.clone());
+        return Ok(());
+    }
+    let d = pollard_rho(n)?;
+    split(&d, primes)?;
+    split(&(n / &d), primes)
+}
+
+// Pollard rho（Brent 变体，每 128 步才做一次 gcd），返回 n 的一个非平凡因子
+fn pollard_rho(n: &BigInt) -> Result<BigInt, String> {
+    let mut steps = 0;
+    for c in 1u32..20 {
+        let c = BigInt::from(c);
+        let f = |x: &BigInt| (x * x + &c) % n;
+        let mut y = BigInt::from(2);
+        let mut r = 1usize;
+        let mut q = BigInt::one();
+        let mut d = BigInt::one();
+        let mut x = y.clone();
+        let mut saved = y.clone();
+        while d.is_one() {
+            x = y.clone();
+            for _ in 0..r {
+                y = f(&y);
+            }
+            let mut k = 0;
+            while k < r && d.is_one() {
+                saved = y.clone();
+                for _ in 0..128.min(r - k) {
+                    y = f(&y);
+                    q = q * (&x - &y).abs() % n;
+                }
+                d = q.gcd(n);
+                k += 128;
+            }
+            r *= 2;
+            steps += r;
+            if steps > RHO_BUDGET {
+                return Err(format!("{} is too hard to factor", n));
+            }
+        }
+        // 批量乘积把因子一起吃掉了，退回逐步求 gcd
+        if d == *n {
+            loop {
+                saved = f(&saved);
+                d = (&x - &saved).abs().gcd(n);
+                if !d.is_one() {
+                    break;
+                }
+            }
+        }
+        if d != *n {
+            return Ok(d);
+        }
+    }
+    Err(format!("{} is too hard to factor", n))
+}
+
+pub fn totient(n: &BigInt) -> Result<BigInt, String> {
+    let mut result = n.clone();
+    for (p, _) in factorize(n)? {
+        result = result / &p * (&p - 1);
+    }
+    Ok(result)
+}
+
+// 模幂，负指数时先求逆元；结果落在 [0, |m|)
+pub fn powmod(base: &BigInt, exp: &BigInt, m: &BigInt) -> Result<BigInt, String> {
+    if m.is_zero() {
+        return Err("modulus must not be 0".to_string());
+    }
+    let m = m.abs();
+    let base = if exp.is_negative() {
+        modinv(base, &m)?
+    } else {
+        base.mod_floor(&m)
+    };
+    Ok(base.modpow(&exp.abs(), &m))
+}
+
+pub fn modinv(a: &BigInt, m: &BigInt) -> Result<BigInt, String> {
+    if m.is_zero() {
+        return Err("modulus must not be 0".to_string());
+    }
+    let m = m.abs();
+    let egcd = a.mod_floor(&m).extended_gcd(&m);
+    if !egcd.gcd.is_one() {
+        return Err(format!("{} has no inverse modulo {}", a, m));
+    }
+    Ok(egcd.x.mod_floor(&m))
+}
@@ -0,0 +1,339 @@
+// 一元多项式：系数用 f64，内部按升幂存储
+use std::fmt::{Display, Formatter};
+
+use num::complex::Complex64;
+
+use super::{large_arguments, Arity, Builtin, FnResult};
+use crate::engine::Value;
+
+pub const FUNCTIONS: &[Builtin] = &[
+    Builtin::new("deriv", Arity::Exact(1), deriv_fn),
+    Builtin::new("peval", Arity::Exact(2), peval_fn),
+    Builtin::new("pdiv", Arity::Exact(2), pdiv_fn),
+    Builtin::new("pgcd", Arity::Exact(2), pgcd_fn),
+    Builtin::new("roots", Arity::Exact(1), roots_fn).slow(large_arguments),
+];
+
+// 小于这个值的系数视为 0（相对于最大系数）
+const EPSILON: f64 = 1e-10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    coeffs: Vec<f64>,
+    var: String,
+}
+
+impl Polynomial {
+    pub fn new(mut coeffs: Vec<f64>, var: &str) -> Self {
+        while coeffs.last() == Some(&0.0) {
+            coeffs.pop();
+        }
+        Polynomial {
+            coeffs,
+            var: var.to_string(),
+        }
+    }
+
+    // poly([1, -3, 2])：按降幂给出的系数，和 numpy / MATLAB 一致
+    pub fn from_descending(coeffs: &[f64], var: &str) -> Self {
+        Polynomial::new(coeffs.iter().rev().copied().collect(), var)
+    }
+
+    pub fn constant(c: f64, var: &str) -> Self {
+        Polynomial::new(vec![c], var)
+    }
+
+    // 多项式 x 本身
+    pub fn identity(var: &str) -> Self {
+        Polynomial::new(vec![0.0, 1.0], var)
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    // 按升幂排列的系数
+    pub fn coeffs(&self) -> &[f64] {
+        &self.coeffs
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.len().saturating_sub(1)
+    }
+
+    fn leading(&self) -> f64 {
+        self.coeffs.last().copied().unwrap_or(0.0)
+    }
+
+    // 把相对很小的系数清零，消除除法和求 GCD 时的舍入噪声
+    fn cleaned(mut self) -> Self {
+        let scale = self.coeffs.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+        for c in &mut self.coeffs {
+            if c.abs() <= EPSILON * scale {
+                *c = 0.0;
+            }
+        }
+        Polynomial::new(self.coeffs, &self.var)
+    }
+
+    pub fn add(&self, rhs: &Polynomial) -> Polynomial {
+        let n = self.coeffs.len().max(rhs.coeffs.len());
+        let coeffs = (0..n)
+            .map(|i| self.coeffs.get(i).unwrap_or(&0.0) + rhs.coeffs.get(i).unwrap_or(&0.0))
+            .collect();
+        Polynomial::new(coeffs, &self.var)
+    }
+
+    pub fn scale(&self, k: f64) -> Polynomial {
+        Polynomial::new(self.coeffs.iter().map(|c| c * k).collect(), &self.var)
+    }
+
+    pub fn sub(&self, rhs: &Polynomial) -> Polynomial {
+        self.add(&rhs.scale(-1.0))
+    }
+
+    pub fn mul(&self, rhs: &Polynomial) -> Polynomial {
+        if self.is_zero() || rhs.is_zero() {
+            return Polynomial::new(Vec::new(), &self.var);
+        }
+        let mut coeffs = vec![0.0; self.coeffs.len() + rhs.coeffs.len() - 1];
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in rhs.coeffs.iter().enumerate() {
+                coeffs[i + j] += a * b;
+            }
+        }
+        Polynomial::new(coeffs, &self.var)
+    }
+
+    // 平方求幂，只要 log2(exp) 次乘法
+    pub fn pow(&self, mut exp: u32) -> Polynomial {
+        let mut result = Polynomial::constant(1.0, &self.var);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    // 长除法，返回 (商, 余数)
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        if divisor.is_zero() {
+            return Err("division by zero polynomial".to_string());
+        }
+        let mut rem = self.coeffs.clone();
+        let d = divisor.degree();
+        if self.coeffs.len() <= d {
+            return Ok((Polynomial::new(Vec::new(), &self.var), self.clone()));
+        }
+        let mut quot = vec![0.0; self.coeffs.len() - d];
+        for i in (0..quot.len()).rev() {
+            let q = rem[i + d] / divisor.leading();
+            quot[i] = q;
+            for (j, c) in divisor.coeffs.iter().enumerate() {
+                rem[i + j] -= q * c;
+            }
+        }
+        rem.truncate(d);
+        Ok((
+            Polynomial::new(quot, &self.var),
+            Polynomial::new(rem, &self.var).cleaned(),
+        ))
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        let coeffs = self
+            .coeffs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, c)| c * i as f64)
+            .collect();
+        Polynomial::new(coeffs, &self.var)
+    }
+
+    // 秦九韶算法
+    pub fn eval(&self, x: f64) -> f64 {
+        self.coeffs.iter().rev().fold(0.0, |acc, c| acc * x + c)
+    }
+
+    pub fn eval_complex(&self, z: Complex64) -> Complex64 {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(Complex64::new(0.0, 0.0), |acc, c| acc * z + c)
+    }
+
+    // 欧几里得算法，结果化为首一多项式
+    pub fn gcd(&self, rhs: &Polynomial) -> Result<Polynomial, String> {
+        let (mut a, mut b) = (self.clone().cleaned(), rhs.clone().cleaned());
+        while !b.is_zero() {
+            let (_, r) = a.div_rem(&b)?;
+            a = b;
+            b = r;
+        }
+        if a.is_zero() {
+            return Ok(a);
+        }
+        let lead = a.leading();
+        Ok(a.scale(1.0 / lead))
+    }
+
+    // Durand-Kerner 迭代同时求出全部复根
+    pub fn roots(&self) -> Vec<Complex64> {
+        let mut coeffs = self.coeffs.clone();
+        // 先把 x = 0 的根单独提出来
+        let zeros = coeffs.iter().take_while(|c| **c == 0.0).count();
+        coeffs.drain(..zeros);
+        let mut roots = vec![Complex64::new(0.0, 0.0); zeros];
+        let n = coeffs.len().saturating_sub(1);
+        if n == 0 {
+            return roots;
+        }
+        let lead = coeffs[n];
+        let monic = Polynomial::new(coeffs.iter().map(|c| c / lead).collect(), &self.var);
+
+        let seed = Complex64::new(0.4, 0.9);
+        let mut z: Vec<Complex64> = (0..n).map(|k| seed.powu(k as u32)).collect();
+        for _ in 0..1000 {
+            let mut change = 0.0f64;
+            for i in 0..n {
+                let mut denom = Complex64::new(1.0, 0.0);
+                for j in 0..n {
+                    if i != j {
+                        denom *= z[i] - z[j];
+                    }
+                }
+                let delta = monic.eval_complex(z[i]) / denom;
+                if delta.is_finite() {
+                    z[i] -= delta;
+                    change = change.max(delta.norm());
+                }
+            }
+            if change < 1e-14 * z.iter().fold(1.0f64, |m, r| m.max(r.norm())) {
+                break;
+            }
+        }
+        // 相对很小的实部或虚部是迭代误差
+        roots.extend(z.into_iter().map(|r| {
+            let tiny = 1e-12 * r.norm().max(1.0);
+            Complex64::new(
+                if r.re.abs() < tiny { 0.0 } else { r.re },
+                if r.im.abs() < tiny { 0.0 } else { r.im },
+            )
+        }));
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        roots
+    }
+}
+
+// 标准代数写法：x^2 - 3x + 2
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut first = true;
+        for (power, &c) in self.coeffs.iter().enumerate().rev() {
+            if c == 0.0 {
+                continue;
+            }
+            let sign = if c < 0.0 { "-" } else { "+" };
+            if first {
+                if c < 0.0 {
+                    write!(f, "-")?;
+                }
+            } else {
+                write!(f, " {} ", sign)?;
+            }
+            first = false;
+            let magnitude = c.abs();
+            if power == 0 || magnitude != 1.0 {
+                write!(f, "{}", magnitude)?;
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "{}", self.var)?,
+                _ => write!(f, "{}^{}", self.var, power)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn as_poly(value: &Value) -> Result<Polynomial, String> {
+    match value {
+        Value::Poly(p) => Ok(p.clone()),
+        other => match other.to_f64() {
+            x if x.is_nan() => Err(format!("expected a polynomial, got {}", other)),
+            x => Ok(Polynomial::constant(x, "x")),
+        },
+    }
+}
+
+fn deriv_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Poly(as_poly(&args[0])?.derivative()))
+}
+
+fn peval_fn(args: &[Value]) -> FnResult {
+    Ok(Value::Float(as_poly(&args[0])?.eval(args[1].to_f64())))
+}
+
+// 两个参数都是多项式时变量必须相同
+fn poly_pair(args: &[Value]) -> Result<(Polynomial, Polynomial), String> {
+    if let (Value::Poly(p), Value::Poly(q)) = (&args[0], &args[1]) {
+        if p.var() != q.var() {
+            return Err(format!(
+                "cannot combine polynomials in {} and {}",
+                p.var(),
+                q.var()
+            ));
+        }
+    }
+    Ok((as_poly(&args[0])?, as_poly(&args[1])?))
+}
+
+fn pdiv_fn(args: &[Value]) -> FnResult {
+    let (p, q) = poly_pair(args)?;
+    let (q, r) = p.div_rem(&q)?;
+    Ok(Value::List(vec![Value::Poly(q), Value::Poly(r)]))
+}
+
+fn pgcd_fn(args: &[Value]) -> FnResult {
+    let (p, q) = poly_pair(args)?;
+    Ok(Value::Poly(p.gcd(&q)?))
+}
+
+fn roots_fn(args: &[Value]) -> FnResult {
+    let p = as_poly(&args[0])?;
+    if p.is_zero() {
+        return Err("the zero polynomial has infinitely many roots".to_string());
+    }
+    Ok(Value::List(
+        p.roots().into_iter().map(Value::from_complex).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Polynomial;
+
+    #[test]
+    fn pow_by_squaring() {
+        let p = Polynomial::from_descending(&[1.0, 1.0], "x");
+        let mut expected = Polynomial::constant(1.0, "x");
+        for exp in 0..12 {
+            assert_eq!(p.pow(exp), expected);
+            expected = expected.mul(&p);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// 随机数函数，使用会话里的随机数发生器，设置种子后结果可复现
+use num::ToPrimitive;
+use rand::seq::index;
+use rand::Rng;
+
+use super::{Arity, Builtin, FnResult};
+use crate::engine::{Context, Value};
+
+pub const FUNCTIONS: &[Builtin] = &[
+    Builtin::session("rand", Arity::Exact(0), rand_fn),
+    Builtin::session("randint", Arity::Exact(2), randint_fn),
+    Builtin::session("sample", Arity::Exact(2), sample_fn),
+    Builtin::session("seed", Arity::Exact(1), seed_fn),
+];
+
+fn rand_fn(ctx: &mut Context, _args: &[Value]) -> FnResult {
+    Ok(Value::Float(ctx.rng().gen()))
+}
+
+// 闭区间 [a, b] 上的均匀整数
+fn randint_fn(ctx: &mut Context, args: &[Value]) -> FnResult {
+    let a = args[0].to_integer()?.to_i64().ok_or("bounds too large")?;
+    let b = args[1].to_integer()?.to_i64().ok_or("bounds too large")?;
+    if a > b {
+        return Err("lower bound exceeds upper bound".to_string());
+    }
+    Ok(Value::Int(ctx.rng().gen_range(a..=b).into()))
+}
+
+// 不放回地抽取 k 个元素
+fn sample_fn(ctx: &mut Context, args: &[Value]) -> FnResult {
+    let items = args[0].as_list()?;
+    let k = args[1]
+        .to_integer()?
+        .to_usize()
+        .filter(|k| *k <= items.len())
+        .ok_or("sample size must be between 0 and the list length")?;
+    let picked = index::sample(ctx.rng(), items.len(), k);
+    Ok(Value::List(
+        picked.iter().map(|i| items[i].clone()).collect(),
+    ))
+}
+
+fn seed_fn(ctx: &mut Context, args: &[Value]) -> FnResult {
+    let seed = args[0].to_integer()?;
+    ctx.set_seed(
+        seed.to_u64()
+            .ok_or("seed must be a non-negative 64-bit integer")?,
+    );
+    Ok(Value::Int(seed))
+}
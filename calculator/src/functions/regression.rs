@@ -0,0 +1,302 @@
+// 最小二乘拟合：线性、多项式、指数、对数、幂函数模型
+use std::fmt::{Display, Formatter};
+
+use super::{Arity, Builtin, FnResult, Polynomial};
+use crate::engine::Value;
+
+pub const FUNCTIONS: &[Builtin] = &[Builtin::new("polyfit", Arity::Exact(3), polyfit_fn)];
+
+// 多项式拟合的最高次数
+pub const MAX_DEGREE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Model {
+    Linear,
+    Polynomial(usize),
+    // y = a·e^(b·x)
+    Exponential,
+    // y = a + b·ln(x)
+    Logarithmic,
+    // y = a·x^b
+    Power,
+}
+
+impl Model {
+    pub const ALL: [Model; 5] = [
+        Model::Linear,
+        Model::Polynomial(2),
+        Model::Exponential,
+        Model::Logarithmic,
+        Model::Power,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Linear => "Linear",
+            Model::Polynomial(_) => "Polynomial",
+            Model::Exponential => "Exponential",
+            Model::Logarithmic => "Logarithmic",
+            Model::Power => "Power",
+        }
+    }
+
+    // 系数的名字，和 Fit 里 coeffs 的顺序一致
+    pub fn coefficient_names(&self) -> Vec<String> {
+        match self {
+            Model::Polynomial(n) => (0..=*n).map(|k| format!("c{}", k)).collect(),
+            _ => vec!["a".to_string(), "b".to_string()],
+        }
+    }
+}
+
+pub struct Fit {
+    pub model: Model,
+    pub coeffs: Vec<f64>,
+    // 在原始的 y 上计算，指数、幂函数模型不是对数变换后的 R²
+    pub r_squared: f64,
+    // y - ŷ，和输入的点一一对应
+    pub residuals: Vec<f64>,
+}
+
+impl Fit {
+    pub fn eval(&self, x: f64) -> f64 {
+        let c = &self.coeffs;
+        match self.model {
+            Model::Linear | Model::Polynomial(_) => c.iter().rev().fold(0.0, |acc, k| acc * x + k),
+            Model::Exponential => c[0] * (c[1] * x).exp(),
+            Model::Logarithmic => c[0] + c[1] * x.ln(),
+            Model::Power => c[0] * x.powf(c[1]),
+        }
+    }
+
+    // 计算器能解析的写法，系数保留完整精度，用来定义函数
+    pub fn expression(&self) -> String {
+        self.render(|c| format!("{}", c))
+    }
+
+    fn render(&self, number: impl Fn(f64) -> String) -> String {
+        let c = &self.coeffs;
+        match self.model {
+            Model::Linear | Model::Polynomial(_) => {
+                let mut text = number(c[0]);
+                for (power, &k) in c.iter().enumerate().skip(1) {
+                    let sign = if k < 0.0 { "-" } else { "+" };
+                    let x = if power == 1 {
+                        "x".to_string()
+                    } else {
+                        format!("x^{}", power)
+                    };
+                    text.push_str(&format!(" {} {}*{}", sign, number(k.abs()), x));
+                }
+                text
+            }
+            Model::Exponential => format!("{}*exp({}*x)", number(c[0]), wrap(number(c[1]))),
+            Model::Logarithmic => {
+                let sign = if c[1] < 0.0 { "-" } else { "+" };
+                format!("{} {} {}*ln(x)", number(c[0]), sign, number(c[1].abs()))
+            }
+            Model::Power => format!("{}*x^{}", number(c[0]), wrap(number(c[1]))),
+        }
+    }
+}
+
+// 界面上显示的公式，系数取 6 位有效数字
+impl Display for Fit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "y = {}", self.render(short))
+    }
+}
+
+// 很小或很大的系数用科学计数法
+fn short(c: f64) -> String {
+    let c: f64 = format!("{:.5e}", c).parse().unwrap_or(c);
+    if c != 0.0 && !(1e-4..1e9).contains(&c.abs()) {
+        format!("{:e}", c)
+    } else {
+        format!("{}", c)
+    }
+}
+
+fn wrap(number: String) -> String {
+    if number.starts_with('-') {
+        format!("({})", number)
+    } else {
+        number
+    }
+}
+
+pub fn fit(model: Model, xs: &[f64], ys: &[f64]) -> Result<Fit, String> {
+    if xs.len() != ys.len() {
+        return Err(format!("{} x values but {} y values", xs.len(), ys.len()));
+    }
+    if xs.iter().chain(ys).any(|v| !v.is_finite()) {
+        return Err("data must be real numbers".to_string());
+    }
+    let need = model.coefficient_names().len();
+    if xs.len() < need {
+        return Err(format!(
+            "{} model needs at least {} points",
+            model.name(),
+            need
+        ));
+    }
+    // 指数、对数、幂函数取对数后按直线拟合
+    let positive = |values: &[f64], what: &str| {
+        if values.iter().all(|&v| v > 0.0) {
+            Ok(values.iter().map(|v| v.ln()).collect::<Vec<f64>>())
+        } else {
+            Err(format!("{} model needs {} > 0", model.name(), what))
+        }
+    };
+    let coeffs = match model {
+        Model::Linear => polynomial(xs, ys, 1)?,
+        Model::Polynomial(n) => polynomial(xs, ys, n)?,
+        Model::Exponential => {
+            let line = polynomial(xs, &positive(ys, "y")?, 1)?;
+            vec![line[0].exp(), line[1]]
+        }
+        Model::Logarithmic => polynomial(&positive(xs, "x")?, ys, 1)?,
+        Model::Power => {
+            let line = polynomial(&positive(xs, "x")?, &positive(ys, "y")?, 1)?;
+            vec![line[0].exp(), line[1]]
+        }
+    };
+    let mut fit = Fit {
+        model,
+        coeffs,
+        r_squared: f64::NAN,
+        residuals: Vec::new(),
+    };
+    fit.residuals = xs.iter().zip(ys).map(|(&x, &y)| y - fit.eval(x)).collect();
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let total: f64 = ys.iter().map(|y| (y - mean).powi(2)).sum();
+    let residual: f64 = fit.residuals.iter().map(|r| r * r).sum();
+    // y 全都相同时拟合是精确的
+    fit.r_squared = if total == 0.0 {
+        1.0
+    } else {
+        1.0 - residual / total
+    };
+    Ok(fit)
+}
+
+// 系数从常数项开始；x 先平移缩放到 [-1, 1] 附近再用 Householder QR 解，高次时也稳定
+fn polynomial(xs: &[f64], ys: &[f64], degree: usize) -> Result<Vec<f64>, String> {
+    if degree > MAX_DEGREE {
+        return Err(format!("degree must be at most {}", MAX_DEGREE));
+    }
+    let (lo, hi) = xs
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| {
+            (lo.min(x), hi.max(x))
+        });
+    let center = (lo + hi) / 2.0;
+    let scale = if hi > lo { (hi - lo) / 2.0 } else { 1.0 };
+    let m = xs.len();
+    let n = degree + 1;
+    // 按列存的范德蒙德矩阵
+    let mut a: Vec<Vec<f64>> = (0..n)
+        .map(|k| {
+            xs.iter()
+                .map(|x| ((x - center) / scale).powi(k as i32))
+                .collect()
+        })
+        .collect();
+    let mut b = ys.to_vec();
+    for k in 0..n {
+        let norm = a[k][k..].iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm <= 1e-12 * (m as f64).sqrt() {
+            return Err(format!(
+                "need at least {} distinct x values for degree {}",
+                n, degree
+            ));
+        }
+        let alpha = if a[k][k] > 0.0 { -norm } else { norm };
+        let mut v = a[k][k..].to_vec();
+        v[0] -= alpha;
+        let vv: f64 = v.iter().map(|t| t * t).sum();
+        for column in a.iter_mut().skip(k).chain(std::iter::once(&mut b)) {
+            let dot: f64 = v.iter().zip(&column[k..]).map(|(p, q)| p * q).sum();
+            let factor = 2.0 * dot / vv;
+            for (target, vi) in column[k..].iter_mut().zip(&v) {
+                *target -= factor * vi;
+            }
+        }
+    }
+    // 回代得到关于 t = (x - center) / scale 的系数
+    let mut t = vec![0.0; n];
+    for k in (0..n).rev() {
+        let sum: f64 = (k + 1..n).map(|j| a[j][k] * t[j]).sum();
+        t[k] = (b[k] - sum) / a[k][k];
+    }
+    // 换回关于 x 的系数
+    let shifted = Polynomial::new(vec![-center / scale, 1.0 / scale], "x");
+    let mut p = Polynomial::new(Vec::new(), "x");
+    for &c in t.iter().rev() {
+        p = p.mul(&shifted).add(&Polynomial::constant(c, "x"));
+    }
+    let mut coeffs = p.coeffs().to_vec();
+    coeffs.resize(n, 0.0);
+    // 相对最大系数可以忽略的是舍入误差，精确数据拟合出 x^2 而不是 x^2 - 1.3e-15x
+    let largest = coeffs.iter().fold(0.0f64, |m, c| m.max(c.abs()));
+    for c in &mut coeffs {
+        if c.abs() < 1e-12 * largest {
+            *c = 0.0;
+        }
+    }
+    Ok(coeffs)
+}
+
+fn numbers(value: &Value) -> Result<Vec<f64>, String> {
+    Ok(value.as_list()?.iter().map(Value::to_f64).collect())
+}
+
+// polyfit([1, 2, 3], [2, 4, 6], 1) = 2x
+fn polyfit_fn(args: &[Value]) -> FnResult {
+    let degree = args[2].to_f64();
+    if degree.fract() != 0.0 || !(1.0..=MAX_DEGREE as f64).contains(&degree) {
+        return Err(format!(
+            "degree must be an integer from 1 to {}",
+            MAX_DEGREE
+        ));
+    }
+    let fit = fit(
+        Model::Polynomial(degree as usize),
+        &numbers(&args[0])?,
+        &numbers(&args[1])?,
+    )?;
+    Ok(Value::Poly(Polynomial::new(fit.coeffs, "x")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Case = (Model, fn(f64) -> f64, &'static [f64]);
+
+    // 没有噪声的数据要原样拟合出系数，R² = 1
+    #[test]
+    fn exact_data_is_recovered() {
+        let xs: Vec<f64> = (1..=8).map(|i| i as f64 * 0.5).collect();
+        let cases: [Case; 5] = [
+            (Model::Linear, |x| 2.0 * x + 1.0, &[1.0, 2.0]),
+            (
+                Model::Polynomial(2),
+                |x| 0.5 * x * x - x + 1.0,
+                &[1.0, -1.0, 0.5],
+            ),
+            (Model::Exponential, |x| 3.0 * (0.5 * x).exp(), &[3.0, 0.5]),
+            (Model::Logarithmic, |x| 1.0 + 2.0 * x.ln(), &[1.0, 2.0]),
+            (Model::Power, |x| 2.0 * x.powf(1.5), &[2.0, 1.5]),
+        ];
+        for (model, f, expected) in cases {
+            let ys: Vec<f64> = xs.iter().map(|&x| f(x)).collect();
+            let fit = fit(model, &xs, &ys).unwrap();
+            assert_eq!(fit.coeffs.len(), expected.len(), "{}", model.name());
+            for (c, e) in fit.coeffs.iter().zip(expected) {
+                assert!((c - e).abs() < 1e-9, "{}: {:?}", model.name(), fit.coeffs);
+            }
+            assert!((fit.r_squared - 1.0).abs() < 1e-12, "{}", model.name());
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// 特殊函数：对数伽马、正则化不完全伽马/贝塔函数，参考 Numerical Recipes
+use std::f64::consts::PI;
+
+const EPS: f64 = 1e-15;
+const TINY: f64 = 1e-300;
+const MAX_ITER: usize = 500;
+
+// 级数和连分式在 x 接近 a 时大约要 √a 项才收敛，迭代次数跟着参数放大，
+// 到了上限还没收敛就返回 NaN，不给出截断后的错误结果
+fn iterations(a: f64) -> usize {
+    MAX_ITER + (20.0 * a.sqrt()) as usize
+}
+
+// Stirling 级数的余项：ln Γ(a) - ((a - 1/2) ln a - a + ln(2π)/2)，a >= 10 时足够精确
+fn stirling_tail(a: f64) -> f64 {
+    let a2 = a * a;
+    (1.0 / 12.0 - (1.0 / 360.0 - 1.0 / (1260.0 * a2)) / a2) / a
+}
+
+// Lanczos 近似（g = 7, n = 9）
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        // 反射公式
+        return (PI / (PI * x).sin()).abs().ln() - ln_gamma(1.0 - x);
+    }
+    const G: f64 = 7.0;
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let mut sum = COEFFS[0];
+    for (i, c) in COEFFS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// 正则化下不完全伽马函数 P(a, x)
+pub fn gamma_p(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        0.0
+    } else if x < a + 1.0 {
+        gamma_series(a, x)
+    } else {
+        1.0 - gamma_continued_fraction(a, x)
+    }
+}
+
+// 正则化上不完全伽马函数 Q(a, x) = 1 - P(a, x)，尾部不损失精度
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        1.0
+    } else if x < a + 1.0 {
+        1.0 - gamma_series(a, x)
+    } else {
+        gamma_continued_fraction(a, x)
+    }
+}
+
+// x^a e^(-x) / Γ(a)；a 很大时各项直接相减会抵消掉有效数字，
+// 改用 x = a(1 + t) 和 Stirling 公式化简后的形式
+fn gamma_front(a: f64, x: f64) -> f64 {
+    if a < 10.0 {
+        return (-x + a * x.ln() - ln_gamma(a)).exp();
+    }
+    let t = (x - a) / a;
+    (a * (t.ln_1p() - t) + 0.5 * (a / (2.0 * PI)).ln() - stirling_tail(a)).exp()
+}
+
+fn gamma_series(a: f64, x: f64) -> f64 {
+    let mut ap = a;
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    for _ in 0..iterations(a) {
+        ap += 1.0;
+        term *= x / ap;
+        sum += term;
+        if term.abs() < sum.abs() * EPS {
+            return sum * gamma_front(a, x);
+        }
+    }
+    f64::NAN
+}
+
+// Lentz 算法求连分式
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..iterations(a) {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPS {
+            return gamma_front(a, x) * h;
+        }
+    }
+    f64::NAN
+}
+
+// 正则化不完全贝塔函数 I_x(a, b)
+pub fn beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = beta_front(a, b, x);
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// x^a (1 - x)^b / B(a, b)；参数都很大时和 gamma_front 一样换成以 a / (a + b) 为中心的形式
+fn beta_front(a: f64, b: f64, x: f64) -> f64 {
+    if a < 10.0 || b < 10.0 {
+        return (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln())
+            .exp();
+    }
+    let x0 = a / (a + b);
+    let u = (x - x0) / x0;
+    let v = (x0 - x) / (1.0 - x0);
+    (a * (u.ln_1p() - u)
+        + b * (v.ln_1p() - v)
+        + 0.5 * (a * b / (2.0 * PI * (a + b))).ln()
+        + stirling_tail(a + b)
+        - stirling_tail(a)
+        - stirling_tail(b))
+    .exp()
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let qab = a + b;
+    let qap = a + 1.0;
+    let qam = a - 1.0;
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..iterations(a.max(b)) {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        // 偶数项
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        // 奇数项
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPS {
+            return h;
+        }
+    }
+    f64::NAN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64, tolerance: f64) -> bool {
+        (actual - expected).abs() <= tolerance * expected.abs()
+    }
+
+    // 参考值用 mpmath 按 40 位精度算出
+    #[test]
+    fn incomplete_functions_match_reference_values() {
+        let cases = [
+            (gamma_p(1.0, 1.0), 0.6321205588285577),
+            (gamma_p(0.5, 2.0), 0.9544997361036416),
+            (gamma_q(10.0, 5.0), 0.9681719426937952),
+            (gamma_q(20.0, 30.0), 0.02187346844139085),
+            (beta_inc(2.0, 3.0, 0.4), 0.5248),
+            (beta_inc(0.5, 0.5, 0.25), 1.0 / 3.0),
+            (beta_inc(30.0, 20.0, 0.55), 0.2328886067865693),
+        ];
+        for (i, (actual, expected)) in cases.into_iter().enumerate() {
+            assert!(close(actual, expected, 1e-10), "case {}: {}", i, actual);
+        }
+    }
+
+    // 原来固定 500 次迭代，这些参数会静默截断，得到 0.81、0.26、0.99 这样的结果
+    #[test]
+    fn large_parameters_converge() {
+        let cases = [
+            // poisscdf(10^6, 10^6)、chi2cdf(10^6, 10^6)
+            (gamma_q(1e6 + 1.0, 1e6), 0.5002659614862837),
+            (gamma_p(5e5, 5e5), 0.5001880631966055),
+            // binomcdf(n/2, n, 0.5) = 1/2 + pmf/2
+            (beta_inc(5e7, 5e7 + 1.0, 0.5), 0.5000398942279404),
+            (beta_inc(5e11, 5e11 + 1.0, 0.5), 0.5000003989422804),
+        ];
+        for (i, (actual, expected)) in cases.into_iter().enumerate() {
+            assert!(close(actual, expected, 1e-9), "case {}: {}", i, actual);
+        }
+    }
+}
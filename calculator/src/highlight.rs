@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId, Stroke};
+
+use crate::engine::{scan, Token, KEYWORDS};
+
+#[derive(Clone, Copy, PartialEq)]
+struct Style {
+    color: Color32,
+    background: Color32,
+    underline: bool,
+}
+
+// 各类记号的颜色，深色和浅色主题各一套
+struct Palette {
+    number: Color32,
+    operator: Color32,
+    function: Color32,
+    variable: Color32,
+    keyword: Color32,
+}
+
+fn palette(dark_mode: bool) -> Palette {
+    if dark_mode {
+        Palette {
+            number: Color32::from_rgb(0xb5, 0xce, 0xa8),
+            operator: Color32::from_rgb(0xc5, 0x86, 0xc0),
+            function: Color32::from_rgb(0xdc, 0xdc, 0xaa),
+            variable: Color32::from_rgb(0x9c, 0xdc, 0xfe),
+            keyword: Color32::from_rgb(0x56, 0x9c, 0xd6),
+        }
+    } else {
+        Palette {
+            number: Color32::from_rgb(0x09, 0x86, 0x58),
+            operator: Color32::from_rgb(0xaf, 0x00, 0xdb),
+            function: Color32::from_rgb(0x79, 0x5e, 0x26),
+            variable: Color32::from_rgb(0x00, 0x10, 0x80),
+            keyword: Color32::from_rgb(0x00, 0x00, 0xff),
+        }
+    }
+}
+
+// 输入框的排版：按记号着色，高亮光标处括号和与之配对的括号，给出错的范围加下划线
+// cursor 是字符下标，和 egui 的 CCursor 一致
+pub fn layout(
+    ui: &egui::Ui,
+    text: &str,
+    cursor: Option<usize>,
+    error: Option<Range<usize>>,
+    font: FontId,
+) -> LayoutJob {
+    let visuals = ui.visuals();
+    let colors = palette(visuals.dark_mode);
+    let plain = Style {
+        color: visuals.text_color(),
+        background: Color32::TRANSPARENT,
+        underline: false,
+    };
+    let mut styles = vec![plain; text.len()];
+    let (lexed, _) = scan(text);
+
+    for (i, (tok, span)) in lexed.tokens.iter().zip(&lexed.spans).enumerate() {
+        let color = match tok {
+            Token::Number(_) => colors.number,
+            Token::Op(_) => colors.operator,
+            Token::Ident(name) if KEYWORDS.contains(&name.as_str()) => colors.keyword,
+            Token::Ident(_) if lexed.tokens.get(i + 1) == Some(&Token::LParen) => colors.function,
+            Token::Ident(_) => colors.variable,
+            _ => continue,
+        };
+        for style in &mut styles[span.clone()] {
+            style.color = color;
+        }
+    }
+
+    // 光标紧挨着的括号优先看左边那个
+    if let Some(cursor) = cursor {
+        let byte = text
+            .char_indices()
+            .nth(cursor)
+            .map_or(text.len(), |(i, _)| i);
+        let partners = match_brackets(&lexed.tokens);
+        let at_cursor = lexed
+            .spans
+            .iter()
+            .position(|span| span.end == byte)
+            .filter(|&i| is_bracket(&lexed.tokens[i]))
+            .or_else(|| {
+                lexed
+                    .spans
+                    .iter()
+                    .position(|span| span.start == byte)
+                    .filter(|&i| is_bracket(&lexed.tokens[i]))
+            });
+        if let Some(i) = at_cursor {
+            match partners[i] {
+                Some(j) => {
+                    for k in [i, j] {
+                        for style in &mut styles[lexed.spans[k].clone()] {
+                            style.background = visuals.selection.bg_fill.gamma_multiply(0.5);
+                        }
+                    }
+                }
+                None => {
+                    for style in &mut styles[lexed.spans[i].clone()] {
+                        style.color = visuals.error_fg_color;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(error) = error {
+        let end = error.end.min(text.len());
+        for style in styles.iter_mut().take(end).skip(error.start) {
+            style.underline = true;
+        }
+    }
+
+    // 相同样式的连续字符合并成一段
+    let mut job = LayoutJob::default();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, _)) = chars.next() {
+        let style = styles[start];
+        let mut end = text.len();
+        while let Some(&(next, _)) = chars.peek() {
+            if styles[next] != style {
+                end = next;
+                break;
+            }
+            chars.next();
+        }
+        let underline = if style.underline {
+            Stroke::new(1.5, visuals.error_fg_color)
+        } else {
+            Stroke::NONE
+        };
+        job.append(
+            &text[start..end],
+            0.0,
+            TextFormat {
+                font_id: font.clone(),
+                color: style.color,
+                background: style.background,
+                underline,
+                ..Default::default()
+            },
+        );
+    }
+    job
+}
+
+fn is_bracket(tok: &Token) -> bool {
+    matches!(
+        tok,
+        Token::LParen | Token::RParen | Token::LBracket | Token::RBracket
+    )
+}
+
+// 用栈给括号配对，类型不一致或者多余的括号没有配对
+fn match_brackets(tokens: &[Token]) -> Vec<Option<usize>> {
+    let mut partners = vec![None; tokens.len()];
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, tok) in tokens.iter().enumerate() {
+        let open = match tok {
+            Token::LParen | Token::LBracket => {
+                stack.push(i);
+                continue;
+            }
+            Token::RParen => Token::LParen,
+            Token::RBracket => Token::LBracket,
+            _ => continue,
+        };
+        if let Some(&j) = stack.last() {
+            if tokens[j] == open {
+                stack.pop();
+                partners[i] = Some(j);
+                partners[j] = Some(i);
+            }
+        }
+    }
+    partners
+}
@@ -0,0 +1,131 @@
+use eframe::egui;
+
+use crate::functions::{amortization_schedule, schedule_to_csv, AmortizationRow};
+
+// "Loan" 面板：生成等额本息还款计划，可以导出 CSV
+pub struct LoanPanel {
+    principal: f64,
+    annual_rate: f64,
+    years: u32,
+    payments_per_year: u32,
+    schedule: Vec<AmortizationRow>,
+    status: Option<String>,
+}
+
+impl Default for LoanPanel {
+    fn default() -> Self {
+        LoanPanel {
+            principal: 100_000.0,
+            annual_rate: 5.0,
+            years: 30,
+            payments_per_year: 12,
+            schedule: Vec::new(),
+            status: None,
+        }
+    }
+}
+
+impl LoanPanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.principal)
+                    .speed(100.0)
+                    .range(0.0..=f64::MAX)
+                    .prefix("principal: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.annual_rate)
+                    .speed(0.05)
+                    .range(0.0..=100.0)
+                    .prefix("rate: ")
+                    .suffix(" %/yr"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.years)
+                    .range(1..=100)
+                    .prefix("term: ")
+                    .suffix(" yr"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.payments_per_year)
+                    .range(1..=365)
+                    .suffix(" payments/yr"),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Generate schedule").clicked() {
+                let rate = self.annual_rate / 100.0 / self.payments_per_year as f64;
+                let periods = self.years * self.payments_per_year;
+                self.schedule = amortization_schedule(self.principal, rate, periods);
+                self.status = None;
+            }
+            if !self.schedule.is_empty() && ui.button("Export CSV").clicked() {
+                self.export_csv();
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        if self.schedule.is_empty() {
+            return;
+        }
+
+        let total_interest: f64 = self.schedule.iter().map(|row| row.interest).sum();
+        ui.label(format!(
+            "payment: {:.2}    total interest: {:.2}",
+            self.schedule[0].payment, total_interest
+        ));
+
+        // 30 年按月还款就有 360 行，只排版看得见的行；第 0 行是表头
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::vertical().max_height(240.0).show_rows(
+            ui,
+            row_height,
+            self.schedule.len() + 1,
+            |ui, visible| {
+                egui::Grid::new("amortization")
+                    .striped(true)
+                    .min_row_height(row_height)
+                    .num_columns(5)
+                    .start_row(visible.start)
+                    .show(ui, |ui| {
+                        for i in visible {
+                            let Some(row) = i.checked_sub(1).map(|i| &self.schedule[i]) else {
+                                for title in ["#", "payment", "interest", "principal", "balance"] {
+                                    ui.strong(title);
+                                }
+                                ui.end_row();
+                                continue;
+                            };
+                            ui.label(row.period.to_string());
+                            for amount in [row.payment, row.interest, row.principal, row.balance] {
+                                ui.label(format!("{:.2}", amount));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            },
+        );
+    }
+
+    fn export_csv(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("amortization.csv")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(
+            match std::fs::write(&path, schedule_to_csv(&self.schedule)) {
+                Ok(()) => format!("saved to {}", path.display()),
+                Err(err) => format!("export failed: {}", err),
+            },
+        );
+    }
+}
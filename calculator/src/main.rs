@@ -1,91 +1,583 @@
+mod autocomplete;
+mod bench;
+mod cli;
+mod clipboard;
+mod engine;
+mod fit_panel;
+mod functions;
+mod highlight;
+mod loan_panel;
+mod memory;
+mod pretty;
+mod preview;
+mod recognize;
+mod rpn;
+mod session;
+mod stats_panel;
+mod table_panel;
+mod tape;
+mod worksheet;
+
+use autocomplete::Autocomplete;
 use eframe::egui;
-use meval::eval_str;
+use engine::{evaluate, CalcError, Context, Statement, Trace, Value};
+use fit_panel::FitPanel;
+use loan_panel::LoanPanel;
+use memory::Memory;
+use preview::LivePreview;
+use recognize::Recognition;
+use rpn::RpnStack;
+use session::Session;
+use stats_panel::StatsPanel;
+use table_panel::TablePanel;
+use tape::Tape;
+use worksheet::Worksheet;
 
 fn main() {
+    // 带参数时走命令行模式，不打开窗口
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
     let options = eframe::NativeOptions::default();
     let _ = eframe::run_native(
         "My Calculator App",
         options,
-        Box::new(|_cc| Ok(Box::new(MyCalculator::default()) as Box<dyn eframe::App>)),
+        Box::new(|cc| Ok(Box::new(MyCalculator::new(cc)) as Box<dyn eframe::App>)),
     );
 }
 
+// 百分号按键的说明
+const PERCENT_HELP: &str = "Percent: 10% = 0.1\n\
+200 + 10% = 220 (adds 10% of 200)\n\
+200 - 10% = 180\n\
+200 * 10% = 20\n\
+50% of 80 = 40\n\
+pct_change(80, 100) = 25\n\
+Use 'mod' for the remainder: 7 mod 3 = 1";
+
+// 超过这个长度的整数结果只显示首尾
+const LONG_DIGITS: usize = 40;
+
+// 大整数只保留前 20 位和后 10 位，中间用省略号
+fn abbreviate(digits: &str) -> String {
+    format!("{}…{}", &digits[..20], &digits[digits.len() - 10..])
+}
+
+// 按 "=" 确认过的一条计算
+struct HistoryEntry {
+    input: String,
+    value: Value,
+}
+
 #[derive(Default)]
 struct MyCalculator {
     input: String,
-    result: Option<f64>,
+    result: Option<Value>,
+    error: Option<CalcError>,
+    context: Context,
+    preview: LivePreview,
+    autocomplete: Autocomplete,
+    history: Vec<HistoryEntry>,
+    worksheet: Worksheet,
+    tape: Tape,
+    stats: StatsPanel,
+    loan: LoanPanel,
+    table: TablePanel,
+    fit: FitPanel,
+    memory: Memory,
+    rpn_mode: bool,
+    rpn: RpnStack,
+    recognition: Recognition,
+    show_steps: bool,
+    // 最近一次确认的求值步骤
+    steps: Option<Trace>,
+    // 输入框上方排版的语句
+    typeset: Option<Statement>,
+    // 文件菜单最近一次操作的结果
+    status: Option<String>,
 }
 
-impl eframe::App for MyCalculator {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("My Calculator");
-            ui.add_space(10.0);
+impl MyCalculator {
+    // 存储寄存器的内容跨重启保留，从 eframe 的存储里读回来
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut calculator = MyCalculator::default();
+        if let Some(storage) = cc.storage {
+            calculator.memory = Memory::load(storage);
+        }
+        calculator
+    }
 
-            // 使用 RichText 设置字体大小
-            ui.add(
-                egui::TextEdit::singleline(&mut self.input)
-                    .font(egui::FontId::proportional(20.0))
-                    .desired_width(f32::INFINITY),
-            );
+    // 在真正的上下文里求值，成功的结果记入历史
+    fn commit(&mut self) {
+        // 步骤在求值之前记录，赋值之类的改动不影响它
+        self.steps = self
+            .show_steps
+            .then(|| engine::trace(&self.input, &self.context).ok())
+            .flatten();
+        match self.evaluate_line(&self.input.clone()) {
+            Ok(value) => {
+                self.result = Some(value);
+                self.error = None;
+            }
+            Err(error) => {
+                self.result = None;
+                self.error = Some(error);
+            }
+        }
+    }
 
-            ui.add_space(10.0);
+    fn evaluate_line(&mut self, line: &str) -> Result<Value, CalcError> {
+        let value = evaluate(line, &mut self.context)?;
+        self.history.push(HistoryEntry {
+            input: line.to_string(),
+            value: value.clone(),
+        });
+        Ok(value)
+    }
 
-            ui.horizontal(|ui| {
-                for button_label in ["1", "2", "3", "+"] {
-                    if ui.button(button_label).clicked() {
-                        self.input.push_str(button_label);
-                    }
-                    ui.add_space(5.0);
+    // 粘贴的多行文本逐行计算，结果都进历史；输入框保持不变，显示最后一个结果或第一个错误
+    fn paste_lines(&mut self, text: &str) {
+        let mut count = 0;
+        let mut last = None;
+        let mut first_error = None;
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            count += 1;
+            match self.evaluate_line(line) {
+                Ok(value) => last = Some(value),
+                Err(error) if first_error.is_none() => {
+                    first_error = Some(CalcError::new(format!("line {}: {}", i + 1, error)));
                 }
-            });
+                Err(_) => {}
+            }
+        }
+        self.status = Some(format!("pasted {} lines into history", count));
+        match first_error {
+            Some(error) => {
+                self.result = None;
+                self.error = Some(error);
+            }
+            None => {
+                self.result = last;
+                self.error = None;
+            }
+        }
+    }
 
-            ui.horizontal(|ui| {
-                for button_label in ["4", "5", "6", "-"] {
-                    if ui.button(button_label).clicked() {
-                        self.input.push_str(button_label);
-                    }
-                    ui.add_space(5.0);
-                }
-            });
+    // 拖进窗口的文件读成列表变量
+    fn load_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for file in dropped {
+            self.status = Some(
+                clipboard::load_dropped(&file, &mut self.context)
+                    .unwrap_or_else(|err| format!("drop failed: {}", err)),
+            );
+        }
+    }
 
-            ui.horizontal(|ui| {
-                for button_label in ["7", "8", "9", "*"] {
-                    if ui.button(button_label).clicked() {
-                        self.input.push_str(button_label);
-                    }
-                    ui.add_space(5.0);
-                }
+    fn save_session(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Session", &["json"])
+            .set_file_name("session.json")
+            .save_file()
+        else {
+            return;
+        };
+        let session = Session::capture(
+            &self.context,
+            self.history
+                .iter()
+                .map(|entry| (entry.input.as_str(), &entry.value)),
+            &self.worksheet.to_text(),
+            self.rpn_mode,
+            self.rpn.stack(),
+        );
+        self.status = Some(match std::fs::write(&path, session.to_json()) {
+            Ok(()) => format!("saved to {}", path.display()),
+            Err(err) => format!("save failed: {}", err),
+        });
+    }
+
+    fn open_session(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Session", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        let loaded = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Session::from_json(&text))
+            .and_then(|session| {
+                let rpn = session.rpn()?;
+                Ok((session.context()?, session.history()?, rpn, session))
             });
+        match loaded {
+            Ok((context, history, rpn, session)) => {
+                self.context = context;
+                self.history = history
+                    .into_iter()
+                    .map(|(input, value)| HistoryEntry { input, value })
+                    .collect();
+                self.rpn_mode = session.settings.rpn_mode;
+                self.rpn.set_stack(rpn);
+                self.worksheet
+                    .load(&session.worksheet.join("\n"), &self.context);
+                self.input.clear();
+                self.result = None;
+                self.error = None;
+                self.status = Some(format!("opened {}", path.display()));
+            }
+            Err(err) => self.status = Some(format!("open failed: {}", err)),
+        }
+    }
 
-            ui.horizontal(|ui| {
-                if ui.button("0").clicked() {
-                    self.input.push('0');
+    // 普通的代数输入：输入框、按键和结果
+    fn algebraic_ui(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        // 用自定义排版做语法高亮，光标位置取上一帧的状态
+        let input_id = egui::Id::new("calculator_input");
+        let cursor = egui::TextEdit::load_state(&ctx, input_id)
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| range.primary.index);
+        let error_span = self.preview.error_span(&self.input);
+        let mut layouter = |ui: &egui::Ui, text: &str, _wrap_width: f32| {
+            let font = egui::FontId::proportional(20.0);
+            let job = highlight::layout(ui, text, cursor, error_span.clone(), font);
+            ui.fonts(|fonts| fonts.layout_job(job))
+        };
+        // 焦点在输入框或者没有焦点时，多行粘贴和复制结果归这里处理
+        let ours = ctx.memory(|m| m.focused().is_none_or(|id| id == input_id));
+        if ours {
+            if let Some(text) = clipboard::take_multiline_paste(&ctx) {
+                self.paste_lines(&text);
+            }
+        }
+        // 在输入框上方按课本格式排版，方便核对；输到一半解析不了时灰显上一次能解析的样子
+        let parsed = engine::parse(&self.input, &self.context).ok();
+        let stale = parsed.is_none();
+        if parsed.is_some() || self.input.trim().is_empty() {
+            self.typeset = parsed;
+        }
+        if let Some(statement) = &self.typeset {
+            let result = self.result.as_ref().or(self.preview.value_for(&self.input));
+            pretty::show(ui, statement, result.filter(|_| !stale), stale);
+        }
+        // 补全列表打开时，方向键、Tab、Enter 由它先处理
+        self.autocomplete.handle_keys(ui, input_id, &mut self.input);
+        let output = egui::TextEdit::singleline(&mut self.input)
+            .id(input_id)
+            .layouter(&mut layouter)
+            .desired_width(f32::INFINITY)
+            .show(ui);
+        let response = output.response;
+        let cursor = output.cursor_range.map(|range| range.primary.ccursor.index);
+        self.autocomplete.update(&self.input, cursor, &self.context);
+        self.autocomplete
+            .show(ui, response.rect, input_id, &mut self.input);
+        let mut submit = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            for button_label in ["1", "2", "3", "+"] {
+                if ui.button(button_label).clicked() {
+                    self.input.push_str(button_label);
                 }
                 ui.add_space(5.0);
-                if ui.button(".").clicked() {
-                    self.input.push('.');
+            }
+        });
+
+        ui.horizontal(|ui| {
+            for button_label in ["4", "5", "6", "-"] {
+                if ui.button(button_label).clicked() {
+                    self.input.push_str(button_label);
                 }
                 ui.add_space(5.0);
-                if ui.button("=").clicked() {
-                    // 使用 meval 库来解析和计算表达式
-                    self.result = eval_str(&self.input).ok();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            for button_label in ["7", "8", "9", "*"] {
+                if ui.button(button_label).clicked() {
+                    self.input.push_str(button_label);
                 }
                 ui.add_space(5.0);
-                if ui.button("/").clicked() {
-                    self.input.push('/');
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("0").clicked() {
+                self.input.push('0');
+            }
+            ui.add_space(5.0);
+            if ui.button(".").clicked() {
+                self.input.push('.');
+            }
+            ui.add_space(5.0);
+            if ui.button("=").clicked() {
+                submit = true;
+            }
+            ui.add_space(5.0);
+            if ui.button("/").clicked() {
+                self.input.push('/');
+            }
+            ui.add_space(5.0);
+            if ui.button("%").on_hover_text(PERCENT_HELP).clicked() {
+                self.input.push('%');
+            }
+        });
+
+        // 存储按键作用于当前显示的结果，确认过的优先，其次是预览
+        let current = self.result.as_ref().or(self.preview.value_for(&self.input));
+        if let Some(text) = self.memory.keys(ui, current) {
+            self.input.push_str(&text);
+        }
+
+        // 键盘按钮改动输入也算编辑，要在确认之前检查
+        if self.preview.refresh(&ctx, &self.input, &self.context) {
+            self.result = None;
+            self.error = None;
+            self.steps = None;
+        }
+        if submit {
+            self.commit();
+        }
+
+        // 输入框里选中了文字时 Ctrl+C 复制选中的部分，否则复制结果；按着 Shift 复制完整精度
+        let selected = egui::TextEdit::load_state(&ctx, input_id)
+            .and_then(|state| state.cursor.char_range())
+            .is_some_and(|range| range.primary != range.secondary);
+        if let (true, false, Some(full)) = (ours, selected, clipboard::copy_requested(&ctx)) {
+            if let Some(value) = self.result.as_ref().or(self.preview.value_for(&self.input)) {
+                let text = if full {
+                    value.to_string()
+                } else {
+                    display_text(value)
+                };
+                ctx.copy_text(text);
+                self.status = Some(if full {
+                    "copied result (full precision)".to_string()
+                } else {
+                    "copied result".to_string()
+                });
+            }
+        }
+
+        ui.add_space(10.0);
+        if let Some(result) = &self.result {
+            show_value(ui, result, egui::Color32::GREEN, &self.recognition);
+        } else if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error.to_string());
+        } else if let Some(preview) = self.preview.value_for(&self.input) {
+            show_value(ui, preview, egui::Color32::GRAY, &self.recognition);
+        } else if let Some(error) = self.preview.syntax_error() {
+            ui.weak(error.to_string());
+        }
+        if let (true, Some(trace)) = (self.show_steps, &self.steps) {
+            show_steps(ui, trace);
+        }
+    }
+
+    // 把输入框里的表达式复制成 LaTeX 或 MathML，方便贴进论文和 wiki
+    fn copy_markup(&mut self, ctx: &egui::Context, mathml: bool) {
+        self.status = Some(match engine::parse(&self.input, &self.context) {
+            Ok(statement) => {
+                let (text, name) = if mathml {
+                    (engine::mathml(&statement), "MathML")
+                } else {
+                    (engine::latex(&statement), "LaTeX")
+                };
+                ctx.copy_text(text);
+                format!("copied as {}", name)
+            }
+            Err(error) => format!("cannot copy: {}", error),
+        });
+    }
+
+    // 逆波兰模式：栈、输入行和存储按键，存储作用于第 1 层
+    fn rpn_ui(&mut self, ui: &mut egui::Ui) {
+        self.rpn.ui(ui, &self.context);
+        if let Some(text) = self.memory.keys(ui, self.rpn.top()) {
+            self.rpn.append_entry(&text);
+        }
+    }
+}
+
+// 结果在界面上的写法，很长的整数只显示首尾
+fn display_text(value: &Value) -> String {
+    let text = value.to_string();
+    if matches!(value, Value::Int(_)) && text.len() > LONG_DIGITS {
+        abbreviate(&text)
+    } else {
+        text
+    }
+}
+
+// 可折叠的求值步骤，第一行是输入本身，之后每行是一次改写
+fn show_steps(ui: &mut egui::Ui, trace: &Trace) {
+    ui.collapsing(format!("Steps ({})", trace.steps.len() - 1), |ui| {
+        for (i, step) in trace.steps.iter().enumerate() {
+            let arrow = if i == 0 { "  " } else { "→ " };
+            ui.monospace(format!("{}{}", arrow, step));
+        }
+        if trace.truncated {
+            ui.weak("… (too many steps, stopped here)");
+        }
+        if let Some(error) = &trace.error {
+            ui.colored_label(egui::Color32::RED, format!("✖ {}", error));
+        }
+    });
+}
+
+// 显示一个结果，很长的整数只显示首尾并提供复制按钮
+fn show_value(ui: &mut egui::Ui, value: &Value, color: egui::Color32, recognition: &Recognition) {
+    let text = value.to_string();
+    let shown = display_text(value);
+    let long_int = shown != text;
+    // 使用 RichText 设置字体大小和颜色
+    ui.label(
+        egui::RichText::new(format!("Result: {}", shown))
+            .size(24.0)
+            .color(color),
+    );
+    // 认出来的分数或常数倍放在结果下面一行，悬停显示连分数展开
+    if let (Some(exact), Value::Float(x)) = (recognition.recognize(value), value) {
+        ui.label(
+            egui::RichText::new(format!("≈ {}", exact))
+                .size(18.0)
+                .color(color),
+        )
+        .on_hover_text(format!(
+            "continued fraction {}",
+            recognize::continued_fraction(*x)
+        ));
+    }
+    if long_int {
+        ui.horizontal(|ui| {
+            let digits = text.trim_start_matches('-').len();
+            ui.label(format!("{} digits", digits));
+            if ui.button("Copy all digits").clicked() {
+                ui.ctx().copy_text(text.clone());
+            }
+        });
+    }
+}
+
+impl eframe::App for MyCalculator {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.memory.save(storage);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.load_dropped_files(ctx);
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop_hint"),
+            ));
+            let rect = ctx.screen_rect();
+            painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop a text or CSV file to load its numbers as lists",
+                egui::FontId::proportional(20.0),
+                egui::Color32::WHITE,
+            );
+        }
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open session…").clicked() {
+                        ui.close_menu();
+                        self.open_session();
+                    }
+                    if ui.button("Save session…").clicked() {
+                        ui.close_menu();
+                        self.save_session();
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    // 导出的是输入框里的表达式，不求值
+                    if ui.button("Copy as LaTeX").clicked() {
+                        ui.close_menu();
+                        self.copy_markup(ctx, false);
+                    }
+                    if ui.button("Copy as MathML").clicked() {
+                        ui.close_menu();
+                        self.copy_markup(ctx, true);
+                    }
+                });
+                if let Some(status) = &self.status {
+                    ui.weak(status);
                 }
             });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("My Calculator");
+                if self.memory.is_set() {
+                    ui.strong("M").on_hover_text("Memory is not zero");
+                }
+            });
+            ui.add_space(10.0);
 
-            if let Some(result) = self.result {
-                ui.add_space(10.0);
-                // 使用 RichText 设置字体大小和颜色
-                ui.label(
-                    egui::RichText::new(format!("Result: {}", result))
-                        .size(24.0)
-                        .color(egui::Color32::GREEN),
-                );
+            if self.rpn_mode {
+                self.rpn_ui(ui);
+            } else {
+                self.algebraic_ui(ui);
             }
+
+            ui.add_space(10.0);
+            ui.collapsing("Settings", |ui| {
+                let mut implicit = self.context.implicit_multiplication();
+                if ui
+                    .checkbox(&mut implicit, "Implicit multiplication (2x = 2*x)")
+                    .changed()
+                {
+                    self.context.set_implicit_multiplication(implicit);
+                }
+                ui.checkbox(
+                    &mut self.rpn_mode,
+                    "RPN mode (reverse Polish, with a stack)",
+                );
+                ui.checkbox(&mut self.show_steps, "Show evaluation steps");
+                self.recognition.settings_ui(ui);
+            });
+            ui.collapsing("History", |ui| {
+                // 最新的在最上面，点击一条把它放回输入框
+                for entry in self.history.iter().rev() {
+                    let line = format!("{} = {}", entry.input, entry.value);
+                    if ui.selectable_label(false, line).clicked() {
+                        self.input = entry.input.clone();
+                    }
+                }
+            });
+            ui.collapsing("Memory", |ui| {
+                self.memory.ui(ui);
+            });
+            ui.collapsing("Worksheet", |ui| {
+                self.worksheet.ui(ui, &self.context);
+            });
+            ui.collapsing("Tape", |ui| {
+                self.tape.ui(ui, &self.context);
+            });
+            ui.collapsing("Stats", |ui| {
+                self.stats.ui(ui, &mut self.context);
+            });
+            ui.collapsing("Loan", |ui| {
+                self.loan.ui(ui);
+            });
+            ui.collapsing("Table", |ui| {
+                self.table.ui(ui, &self.context);
+            });
+            ui.collapsing("Fit", |ui| {
+                self.fit.ui(ui, &mut self.context);
+            });
         });
     }
 }
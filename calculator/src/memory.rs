@@ -0,0 +1,190 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::Value;
+use crate::session::SavedValue;
+
+// 编号寄存器的个数，STO 0 到 STO 9
+const REGISTERS: usize = 10;
+// 在 eframe 存储里的键
+const STORAGE_KEY: &str = "memory";
+
+// 计算器的存储：M+ / M− 累加的主存储和十个编号寄存器，只放实数
+#[derive(Default)]
+pub struct Memory {
+    value: Option<Value>,
+    registers: [Option<Value>; REGISTERS],
+    // STO / RCL 当前选中的寄存器
+    register: usize,
+    status: Option<String>,
+}
+
+// 退出时写入 eframe 存储的内容
+#[derive(Serialize, Deserialize)]
+struct SavedMemory {
+    value: Option<SavedValue>,
+    registers: Vec<Option<SavedValue>>,
+}
+
+impl Memory {
+    pub fn load(storage: &dyn eframe::Storage) -> Memory {
+        let mut memory = Memory::default();
+        let Some(saved) = eframe::get_value::<SavedMemory>(storage, STORAGE_KEY) else {
+            return memory;
+        };
+        memory.value = saved.value.and_then(|v| v.to_value().ok());
+        for (slot, saved) in memory.registers.iter_mut().zip(saved.registers) {
+            *slot = saved.and_then(|v| v.to_value().ok());
+        }
+        memory
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        let saved = SavedMemory {
+            value: self.value.as_ref().map(SavedValue::from),
+            registers: self
+                .registers
+                .iter()
+                .map(|slot| slot.as_ref().map(SavedValue::from))
+                .collect(),
+        };
+        eframe::set_value(storage, STORAGE_KEY, &saved);
+    }
+
+    // 主存储不为零时显示 M 标记
+    pub fn is_set(&self) -> bool {
+        self.value.as_ref().is_some_and(|v| v.to_f64() != 0.0)
+    }
+
+    // 存储按键；current 是当前显示的结果，返回要插入输入框的文本
+    pub fn keys(&mut self, ui: &mut egui::Ui, current: Option<&Value>) -> Option<String> {
+        let mut insert = None;
+        ui.horizontal(|ui| {
+            if ui.button("MC").on_hover_text("Clear memory").clicked() {
+                self.value = None;
+                self.status = None;
+            }
+            if ui.button("MR").on_hover_text("Recall memory").clicked() {
+                insert = self.recall(self.value.clone(), "memory");
+            }
+            if ui
+                .button("M+")
+                .on_hover_text("Add the result to memory")
+                .clicked()
+            {
+                self.accumulate(current, Value::add);
+            }
+            if ui
+                .button("M−")
+                .on_hover_text("Subtract the result from memory")
+                .clicked()
+            {
+                self.accumulate(current, Value::sub);
+            }
+            ui.separator();
+            if ui
+                .button("STO")
+                .on_hover_text("Store the result in the register")
+                .clicked()
+            {
+                self.status = None;
+                match real(current) {
+                    Ok(value) => self.registers[self.register] = Some(value),
+                    Err(message) => self.status = Some(message),
+                }
+            }
+            if ui
+                .button("RCL")
+                .on_hover_text("Recall the register")
+                .clicked()
+            {
+                let name = format!("register {}", self.register);
+                insert = self.recall(self.registers[self.register].clone(), &name);
+            }
+            egui::ComboBox::from_id_salt("memory_register")
+                .width(40.0)
+                .selected_text(self.register.to_string())
+                .show_ui(ui, |ui| {
+                    for n in 0..REGISTERS {
+                        let label = match &self.registers[n] {
+                            Some(value) => format!("{}: {}", n, value),
+                            None => n.to_string(),
+                        };
+                        ui.selectable_value(&mut self.register, n, label);
+                    }
+                });
+        });
+        if let Some(status) = &self.status {
+            ui.weak(status);
+        }
+        insert
+    }
+
+    fn accumulate(
+        &mut self,
+        current: Option<&Value>,
+        op: fn(&Value, &Value) -> Result<Value, String>,
+    ) {
+        self.status = None;
+        let result = real(current).and_then(|value| {
+            let memory = self.value.clone().unwrap_or(Value::Int(0.into()));
+            op(&memory, &value)
+        });
+        match result {
+            Ok(value) => self.value = Some(value),
+            Err(message) => self.status = Some(message),
+        }
+    }
+
+    // 负数加上括号，接在运算符后面也不会出错
+    fn recall(&mut self, value: Option<Value>, name: &str) -> Option<String> {
+        self.status = None;
+        let Some(value) = value else {
+            self.status = Some(format!("{} is empty", name));
+            return None;
+        };
+        let text = value.to_string();
+        Some(if text.starts_with('-') {
+            format!("({})", text)
+        } else {
+            text
+        })
+    }
+
+    // 各寄存器的内容
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        match &self.value {
+            Some(value) => ui.label(format!("M = {}", value)),
+            None => ui.weak("M is empty"),
+        };
+        egui::Grid::new("memory_registers").show(ui, |ui| {
+            for (n, slot) in self.registers.iter_mut().enumerate() {
+                let Some(value) = slot else {
+                    continue;
+                };
+                ui.monospace(format!("R{}", n));
+                ui.label(value.to_string());
+                if ui
+                    .small_button("✖")
+                    .on_hover_text("Clear register")
+                    .clicked()
+                {
+                    *slot = None;
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+
+// 存储里只放实数，布尔值按 1 / 0 存
+fn real(value: Option<&Value>) -> Result<Value, String> {
+    let Some(value) = value else {
+        return Err("no result to store".to_string());
+    };
+    match value {
+        Value::Int(_) | Value::Factors(_) | Value::Bool(_) => Ok(Value::Int(value.to_integer()?)),
+        Value::Float(_) | Value::Estimate(..) => Ok(Value::Float(value.to_f64())),
+        other => Err(format!("memory holds real numbers only, got {}", other)),
+    }
+}
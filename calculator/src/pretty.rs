@@ -0,0 +1,491 @@
+// 按课本的样子排版表达式：分数上下叠放，指数写成上标，根号和矩阵括号用线画出来
+use std::sync::Arc;
+
+use eframe::egui::{self, epaint::text::Fonts, Color32, FontId, Galley, Pos2, Stroke, Vec2};
+
+use crate::engine::{BinaryOp, Expr, Statement, UnaryOp, Value};
+
+const SIZE: f32 = 20.0;
+// 上标的字号比例，最小不低于 MIN_SIZE
+const SCRIPT: f32 = 0.7;
+const MIN_SIZE: f32 = 10.0;
+
+enum Piece {
+    // 文字左上角的位置
+    Text(Vec2, Arc<Galley>),
+    Line(Vec<Vec2>),
+}
+
+#[derive(Clone, Copy)]
+enum Delimiter {
+    Paren,
+    Bracket,
+    Bar,
+}
+
+// 排好的一块：宽度和数学轴上下的高度，内容的坐标以左端的轴线为原点
+struct MathBox {
+    width: f32,
+    ascent: f32,
+    descent: f32,
+    pieces: Vec<Piece>,
+}
+
+impl MathBox {
+    fn empty() -> Self {
+        MathBox {
+            width: 0.0,
+            ascent: 0.0,
+            descent: 0.0,
+            pieces: Vec::new(),
+        }
+    }
+
+    fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    // 把另一块放在 offset 处，不改变宽度
+    fn place(&mut self, other: MathBox, offset: Vec2) {
+        self.ascent = self.ascent.max(other.ascent - offset.y);
+        self.descent = self.descent.max(other.descent + offset.y);
+        self.pieces
+            .extend(other.pieces.into_iter().map(|piece| match piece {
+                Piece::Text(pos, galley) => Piece::Text(pos + offset, galley),
+                Piece::Line(points) => {
+                    Piece::Line(points.into_iter().map(|p| p + offset).collect())
+                }
+            }));
+    }
+
+    // 接在右边，轴线对齐
+    fn append(&mut self, other: MathBox) {
+        let width = other.width;
+        self.place(other, Vec2::new(self.width, 0.0));
+        self.width += width;
+    }
+
+    fn row(boxes: impl IntoIterator<Item = MathBox>) -> Self {
+        let mut row = MathBox::empty();
+        for item in boxes {
+            row.append(item);
+        }
+        row
+    }
+}
+
+struct Typesetter<'a> {
+    fonts: &'a Fonts,
+    color: Color32,
+}
+
+impl Typesetter<'_> {
+    fn text(&self, text: &str, size: f32) -> MathBox {
+        let galley =
+            self.fonts
+                .layout_no_wrap(text.to_string(), FontId::proportional(size), self.color);
+        let Vec2 {
+            x: width,
+            y: height,
+        } = galley.size();
+        MathBox {
+            width,
+            ascent: height / 2.0,
+            descent: height / 2.0,
+            pieces: vec![Piece::Text(Vec2::new(0.0, -height / 2.0), galley)],
+        }
+    }
+
+    // 两边留空的运算符
+    fn operator(&self, symbol: &str, size: f32) -> MathBox {
+        let mut row = MathBox::empty();
+        row.width = size * 0.25;
+        row.append(self.text(symbol, size));
+        row.width += size * 0.25;
+        row
+    }
+
+    fn statement(&self, statement: &Statement) -> MathBox {
+        match statement {
+            Statement::Expr(expr) => self.expr(expr, SIZE),
+            Statement::Assign(name, expr) => MathBox::row([
+                self.text(name, SIZE),
+                self.operator("=", SIZE),
+                self.expr(expr, SIZE),
+            ]),
+            Statement::Define(name, params, body) => MathBox::row([
+                self.text(&format!("{}({})", name, params.join(", ")), SIZE),
+                self.operator("=", SIZE),
+                self.expr(body, SIZE),
+            ]),
+        }
+    }
+
+    fn expr(&self, expr: &Expr, size: f32) -> MathBox {
+        match expr {
+            Expr::Literal(value) => self.value(value, size),
+            Expr::Variable(name) => self.text(symbol_name(name), size),
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                MathBox::row([self.text("−", size), self.operand(operand, 7, size)])
+            }
+            Expr::Unary(UnaryOp::Not, operand) => MathBox::row([
+                self.text("not", size),
+                self.operator("", size),
+                self.operand(operand, 3, size),
+            ]),
+            Expr::Binary(BinaryOp::Div, lhs, rhs) => {
+                self.fraction(self.expr(lhs, size), self.expr(rhs, size), size)
+            }
+            Expr::Binary(BinaryOp::Pow, base, exponent) => {
+                // 指数本身就和底数分开了，不用括号；底数只有单独的数、名字和调用不加括号
+                let simple = matches!(**base, Expr::Variable(_) | Expr::Call(..) | Expr::List(_))
+                    || matches!(**base, Expr::Literal(_)) && base.precedence() == 10;
+                let base = if simple {
+                    self.expr(base, size)
+                } else {
+                    self.delimited(self.expr(base, size), Delimiter::Paren, size)
+                };
+                let script = (size * SCRIPT).max(MIN_SIZE);
+                self.superscript(base, self.expr(exponent, script), size)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (symbol, left, right) = match op {
+                    BinaryOp::Add => ("+", 5, 6),
+                    BinaryOp::Sub => ("−", 5, 6),
+                    BinaryOp::Mul => ("·", 6, 7),
+                    BinaryOp::Rem => ("mod", 6, 7),
+                    BinaryOp::Lt => ("<", 5, 5),
+                    BinaryOp::Le => ("≤", 5, 5),
+                    BinaryOp::Gt => (">", 5, 5),
+                    BinaryOp::Ge => ("≥", 5, 5),
+                    BinaryOp::Eq => ("=", 5, 5),
+                    BinaryOp::Ne => ("≠", 5, 5),
+                    BinaryOp::Div | BinaryOp::Pow => unreachable!(),
+                };
+                MathBox::row([
+                    self.operand(lhs, left, size),
+                    self.operator(symbol, size),
+                    self.operand(rhs, right, size),
+                ])
+            }
+            Expr::And(lhs, rhs) => MathBox::row([
+                self.operand(lhs, 2, size),
+                self.operator("and", size),
+                self.operand(rhs, 3, size),
+            ]),
+            Expr::Or(lhs, rhs) => MathBox::row([
+                self.operand(lhs, 1, size),
+                self.operator("or", size),
+                self.operand(rhs, 2, size),
+            ]),
+            Expr::If(cond, then, otherwise) => MathBox::row([
+                self.text("if", size),
+                self.operator("", size),
+                self.expr(cond, size),
+                self.operator("then", size),
+                self.expr(then, size),
+                self.operator("else", size),
+                self.expr(otherwise, size),
+            ]),
+            Expr::Percent(operand) => {
+                MathBox::row([self.operand(operand, 9, size), self.text("%", size)])
+            }
+            Expr::Call(name, args) => match (name.as_str(), args.as_slice()) {
+                ("sqrt", [arg]) => self.radical(self.expr(arg, size), size),
+                ("abs", [arg]) => self.delimited(self.expr(arg, size), Delimiter::Bar, size),
+                _ => MathBox::row([
+                    self.text(name, size),
+                    self.delimited(self.items(args, size), Delimiter::Paren, size),
+                ]),
+            },
+            Expr::List(items) => {
+                // 每一项都是等长的列表时画成矩阵
+                let rows: Option<Vec<Vec<MathBox>>> = items
+                    .iter()
+                    .map(|item| match item {
+                        Expr::List(row) if row.len() == items_width(items) => {
+                            Some(row.iter().map(|cell| self.expr(cell, size)).collect())
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                match rows {
+                    Some(rows) if !rows.is_empty() => self.matrix(rows, size),
+                    _ => self.delimited(self.items(items, size), Delimiter::Bracket, size),
+                }
+            }
+        }
+    }
+
+    // 优先级不够时加括号，分数线本身就能分组，当作最高优先级
+    fn operand(&self, expr: &Expr, min: u8, size: f32) -> MathBox {
+        let precedence = match expr {
+            Expr::Binary(BinaryOp::Div, ..) => 10,
+            _ => expr.precedence(),
+        };
+        let inner = self.expr(expr, size);
+        if precedence < min {
+            self.delimited(inner, Delimiter::Paren, size)
+        } else {
+            inner
+        }
+    }
+
+    fn items(&self, items: &[Expr], size: f32) -> MathBox {
+        let mut row = MathBox::empty();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                row.append(self.text(", ", size));
+            }
+            row.append(self.expr(item, size));
+        }
+        row
+    }
+
+    fn value(&self, value: &Value, size: f32) -> MathBox {
+        match value {
+            Value::List(items) if !items.is_empty() => {
+                let width = match &items[0] {
+                    Value::List(row) => row.len(),
+                    _ => 0,
+                };
+                let is_matrix = width > 0
+                    && items
+                        .iter()
+                        .all(|item| matches!(item, Value::List(row) if row.len() == width));
+                if is_matrix {
+                    let rows = items
+                        .iter()
+                        .map(|item| match item {
+                            Value::List(row) => row.iter().map(|v| self.value(v, size)).collect(),
+                            _ => Vec::new(),
+                        })
+                        .collect();
+                    return self.matrix(rows, size);
+                }
+                let mut row = MathBox::empty();
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        row.append(self.text(", ", size));
+                    }
+                    row.append(self.value(item, size));
+                }
+                self.delimited(row, Delimiter::Bracket, size)
+            }
+            // 质因数分解写成带上标的乘积
+            Value::Factors(factors) if !factors.is_empty() => {
+                let script = (size * SCRIPT).max(MIN_SIZE);
+                let mut row = MathBox::empty();
+                for (i, (p, k)) in factors.iter().enumerate() {
+                    if i > 0 {
+                        row.append(self.operator("·", size));
+                    }
+                    let base = self.text(&p.to_string(), size);
+                    if *k == 1 {
+                        row.append(base);
+                    } else {
+                        row.append(self.superscript(base, self.text(&k.to_string(), script), size));
+                    }
+                }
+                row
+            }
+            _ => self.text(&crate::display_text(value).replacen('-', "−", 1), size),
+        }
+    }
+
+    fn fraction(&self, numerator: MathBox, denominator: MathBox, size: f32) -> MathBox {
+        let gap = size * 0.15;
+        let width = numerator.width.max(denominator.width) + 2.0 * gap;
+        let mut frac = MathBox::empty();
+        frac.width = width;
+        let offset = Vec2::new((width - numerator.width) / 2.0, -(gap + numerator.descent));
+        frac.place(numerator, offset);
+        let offset = Vec2::new((width - denominator.width) / 2.0, gap + denominator.ascent);
+        frac.place(denominator, offset);
+        frac.pieces.push(Piece::Line(vec![
+            Vec2::new(gap / 2.0, 0.0),
+            Vec2::new(width - gap / 2.0, 0.0),
+        ]));
+        frac
+    }
+
+    // 上标的底边抬到底数的上半部分，底数很高（比如分数）时跟着抬高
+    fn superscript(&self, base: MathBox, exponent: MathBox, size: f32) -> MathBox {
+        let rise = (base.ascent * 0.6).max(exponent.descent * 0.5 + size * 0.15);
+        let mut boxed = base;
+        let offset = Vec2::new(boxed.width + size * 0.05, -rise);
+        boxed.width = offset.x + exponent.width;
+        boxed.place(exponent, offset);
+        boxed
+    }
+
+    fn radical(&self, content: MathBox, size: f32) -> MathBox {
+        let gap = size * 0.15;
+        let sign = size * 0.6;
+        let top = -(content.ascent + gap);
+        let bottom = content.descent;
+        let at = |t: f32| top + t * (bottom - top);
+        let mut root = MathBox::empty();
+        root.pieces.push(Piece::Line(vec![
+            Vec2::new(0.0, at(0.6)),
+            Vec2::new(sign * 0.25, at(0.5)),
+            Vec2::new(sign * 0.55, bottom),
+            Vec2::new(sign, top),
+            Vec2::new(sign + content.width + gap, top),
+        ]));
+        root.width = sign + content.width + gap;
+        root.place(content, Vec2::new(sign + gap / 2.0, 0.0));
+        root.ascent += 1.0;
+        root
+    }
+
+    // 两边加上和内容一样高的括号
+    fn delimited(&self, content: MathBox, delimiter: Delimiter, size: f32) -> MathBox {
+        let pad = size * 0.05;
+        let top = -(content.ascent + pad);
+        let bottom = content.descent + pad;
+        let width = (size * 0.3).max(content.height() * 0.1);
+        let mut boxed = MathBox::empty();
+        boxed.pieces.push(Piece::Line(delimiter_path(
+            delimiter, width, top, bottom, false,
+        )));
+        boxed.width = width + pad;
+        boxed.append(content);
+        boxed.width += pad;
+        let mut right = delimiter_path(delimiter, width, top, bottom, true);
+        for point in &mut right {
+            point.x += boxed.width;
+        }
+        boxed.pieces.push(Piece::Line(right));
+        boxed.width += width;
+        boxed.ascent = boxed.ascent.max(-top);
+        boxed.descent = boxed.descent.max(bottom);
+        boxed
+    }
+
+    // 各列按最宽的格子对齐，格子在列里居中，整体放在方括号里
+    fn matrix(&self, rows: Vec<Vec<MathBox>>, size: f32) -> MathBox {
+        let column_gap = size * 0.8;
+        let row_gap = size * 0.3;
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<f32> = (0..columns)
+            .map(|j| {
+                rows.iter()
+                    .filter_map(|row| row.get(j))
+                    .map(|cell| cell.width)
+                    .fold(0.0, f32::max)
+            })
+            .collect();
+        let heights: Vec<(f32, f32)> = rows
+            .iter()
+            .map(|row| {
+                row.iter().fold((0.0, 0.0), |(a, d), cell| {
+                    (cell.ascent.max(a), cell.descent.max(d))
+                })
+            })
+            .collect();
+        let total = heights.iter().map(|(a, d)| a + d).sum::<f32>()
+            + row_gap * rows.len().saturating_sub(1) as f32;
+        let mut grid = MathBox::empty();
+        grid.width = widths.iter().sum::<f32>() + column_gap * columns.saturating_sub(1) as f32;
+        let mut y = -total / 2.0;
+        for (row, (ascent, descent)) in rows.into_iter().zip(heights) {
+            let mut x = 0.0;
+            for (cell, width) in row.into_iter().zip(&widths) {
+                let offset = Vec2::new(x + (width - cell.width) / 2.0, y + ascent);
+                grid.place(cell, offset);
+                x += width + column_gap;
+            }
+            y += ascent + descent + row_gap;
+        }
+        grid.ascent = grid.ascent.max(total / 2.0);
+        grid.descent = grid.descent.max(total / 2.0);
+        self.delimited(grid, Delimiter::Bracket, size)
+    }
+}
+
+fn items_width(items: &[Expr]) -> usize {
+    match items.first() {
+        Some(Expr::List(row)) if !row.is_empty() => row.len(),
+        _ => usize::MAX,
+    }
+}
+
+fn symbol_name(name: &str) -> &str {
+    match name {
+        "pi" => "π",
+        "inf" => "∞",
+        _ => name,
+    }
+}
+
+// 括号的折线，right 表示右边的那一个（左右对称）
+fn delimiter_path(
+    delimiter: Delimiter,
+    width: f32,
+    top: f32,
+    bottom: f32,
+    right: bool,
+) -> Vec<Vec2> {
+    let points: Vec<Vec2> = match delimiter {
+        Delimiter::Paren => (0..=16)
+            .map(|i| {
+                let t = i as f32 / 16.0;
+                let x = width * (0.8 - 0.55 * (t * std::f32::consts::PI).sin());
+                Vec2::new(x, top + t * (bottom - top))
+            })
+            .collect(),
+        Delimiter::Bracket => vec![
+            Vec2::new(width * 0.8, top),
+            Vec2::new(width * 0.3, top),
+            Vec2::new(width * 0.3, bottom),
+            Vec2::new(width * 0.8, bottom),
+        ],
+        Delimiter::Bar => vec![Vec2::new(width * 0.5, top), Vec2::new(width * 0.5, bottom)],
+    };
+    if right {
+        points
+            .into_iter()
+            .map(|p| Vec2::new(width - p.x, p.y))
+            .collect()
+    } else {
+        points
+    }
+}
+
+// 排版一行输入，能算出结果时在后面接上 "= 结果"；太宽时可以横向滚动，dimmed 时用淡色
+pub fn show(ui: &mut egui::Ui, statement: &Statement, result: Option<&Value>, dimmed: bool) {
+    let color = if dimmed {
+        ui.visuals().weak_text_color()
+    } else {
+        ui.visuals().text_color()
+    };
+    let math = ui.fonts(|fonts| {
+        let typesetter = Typesetter { fonts, color };
+        let mut math = typesetter.statement(statement);
+        if let (Some(value), false) = (result, matches!(statement, Statement::Define(..))) {
+            math.append(typesetter.operator("=", SIZE));
+            math.append(typesetter.value(value, SIZE));
+        }
+        math
+    });
+    egui::ScrollArea::horizontal()
+        .id_salt("typeset_input")
+        .show(ui, |ui| {
+            let margin = 4.0;
+            let size = Vec2::new(math.width, math.height()) + Vec2::splat(2.0 * margin);
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let origin = Pos2::new(rect.left() + margin, rect.top() + margin + math.ascent);
+            let painter = ui.painter_at(rect);
+            let stroke = Stroke::new(1.2, color);
+            for piece in math.pieces {
+                match piece {
+                    Piece::Text(pos, galley) => painter.galley(origin + pos, galley, color),
+                    Piece::Line(points) => {
+                        let points = points.into_iter().map(|p| origin + p).collect();
+                        painter.add(egui::Shape::line(points, stroke));
+                    }
+                }
+            }
+        });
+}
@@ -0,0 +1,69 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::engine::{evaluate, CalcError, Context, Value};
+
+// 停止输入多久之后才计算预览
+const DEBOUNCE: Duration = Duration::from_millis(150);
+// 预览最多求值的节点数，超过就放弃，不让界面卡住；慢的内置函数另外跳过
+const PREVIEW_STEPS: u64 = 100_000;
+
+// 输入时的实时预览：在上下文的副本上求值，不会改变变量、随机数状态
+#[derive(Default)]
+pub struct LivePreview {
+    seen: String,
+    edited_at: f64,
+    evaluated: String,
+    value: Option<Value>,
+    error: Option<CalcError>,
+}
+
+impl LivePreview {
+    // 每帧调用一次，输入有变化时返回 true
+    pub fn refresh(&mut self, egui_ctx: &egui::Context, input: &str, context: &Context) -> bool {
+        let now = egui_ctx.input(|i| i.time);
+        let changed = input != self.seen;
+        if changed {
+            self.seen = input.to_string();
+            self.edited_at = now;
+        }
+        if input != self.evaluated {
+            let waited = Duration::from_secs_f64((now - self.edited_at).max(0.0));
+            if waited >= DEBOUNCE {
+                let mut scratch = context.clone();
+                scratch.limit_steps(PREVIEW_STEPS);
+                scratch.skip_slow_functions();
+                let result = evaluate(input, &mut scratch);
+                self.value = result.as_ref().ok().cloned();
+                self.error = result.err();
+                self.evaluated = input.to_string();
+            } else {
+                egui_ctx.request_repaint_after(DEBOUNCE - waited);
+            }
+        }
+        changed
+    }
+
+    // 去抖期间预览还是上一次输入的结果，和当前输入对得上时才返回
+    pub fn value_for(&self, input: &str) -> Option<&Value> {
+        if input != self.evaluated {
+            return None;
+        }
+        self.value.as_ref()
+    }
+
+    // 只关心能定位到输入位置的错误，也就是词法和语法错误
+    pub fn syntax_error(&self) -> Option<&CalcError> {
+        self.error.as_ref().filter(|error| error.span().is_some())
+    }
+
+    // 预览对应的正是当前输入时才返回出错范围
+    pub fn error_span(&self, input: &str) -> Option<Range<usize>> {
+        if input != self.evaluated {
+            return None;
+        }
+        self.syntax_error().and_then(CalcError::span)
+    }
+}
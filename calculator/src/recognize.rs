@@ -0,0 +1,157 @@
+use std::f64::consts::{E, PI};
+
+use eframe::egui;
+
+use crate::engine::Value;
+
+// 识别时尝试的常数，结果写成它的有理数倍
+const CONSTANTS: [(&str, f64); 9] = [
+    ("", 1.0),
+    ("π", PI),
+    ("√2", std::f64::consts::SQRT_2),
+    ("√3", 1.732_050_807_568_877_2),
+    ("√5", 2.236_067_977_499_79),
+    ("√6", 2.449_489_742_783_178),
+    ("√7", 2.645_751_311_064_590_7),
+    ("e", E),
+    ("π²", PI * PI),
+];
+
+// 连分数最多展开的项数
+const MAX_TERMS: usize = 32;
+
+// 把近似的浮点结果认成简单的分数或常数的倍数：0.333333 → 1/3，1.5707963 → π/2
+pub struct Recognition {
+    pub enabled: bool,
+    // 允许的绝对误差
+    pub tolerance: f64,
+    pub max_denominator: u64,
+}
+
+impl Default for Recognition {
+    fn default() -> Self {
+        Recognition {
+            enabled: true,
+            tolerance: 1e-6,
+            max_denominator: 100,
+        }
+    }
+}
+
+impl Recognition {
+    // 浮点数结果能认出来时返回它的写法，整数和认出来还是整数的不算
+    pub fn recognize(&self, value: &Value) -> Option<String> {
+        let Value::Float(x) = value else {
+            return None;
+        };
+        let x = *x;
+        if !self.enabled || !x.is_finite() || x == 0.0 {
+            return None;
+        }
+        // 所有常数里分母最小的那个，分母相同时按表里的顺序
+        let mut best: Option<(u64, String)> = None;
+        for (name, constant) in CONSTANTS {
+            let Some((p, q)) = rational(
+                x / constant,
+                self.tolerance / constant,
+                self.max_denominator,
+            ) else {
+                continue;
+            };
+            if p == 0 || (name.is_empty() && q == 1) {
+                continue;
+            }
+            if best.as_ref().is_none_or(|(d, _)| q < *d) {
+                best = Some((q, format_multiple(p, q, name)));
+            }
+        }
+        best.map(|(_, text)| text)
+    }
+
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(
+            &mut self.enabled,
+            "Recognize fractions and constants (0.333… = 1/3)",
+        );
+        ui.add_enabled_ui(self.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Tolerance");
+                ui.add(
+                    egui::DragValue::new(&mut self.tolerance)
+                        .range(1e-15..=1e-2)
+                        .speed(1e-7)
+                        .custom_formatter(|x, _| format!("{:e}", x)),
+                );
+                ui.label("Max denominator");
+                ui.add(egui::DragValue::new(&mut self.max_denominator).range(1..=1_000_000));
+            });
+        });
+    }
+}
+
+// 用连分数的渐近分数逼近 x，返回分母最小的、误差在 tolerance 以内的 p/q
+fn rational(x: f64, tolerance: f64, max_denominator: u64) -> Option<(i64, u64)> {
+    if x.abs() > 1e12 {
+        return None;
+    }
+    let (mut p0, mut q0, mut p1, mut q1) = (0i128, 1i128, 1i128, 0i128);
+    let mut r = x.abs();
+    for _ in 0..MAX_TERMS {
+        let a = r.floor();
+        let (p, q) = (a as i128 * p1 + p0, a as i128 * q1 + q0);
+        if q > max_denominator as i128 {
+            return None;
+        }
+        if (p as f64 / q as f64 - x.abs()).abs() <= tolerance {
+            let sign = if x < 0.0 { -1 } else { 1 };
+            return Some((sign * p as i64, q as u64));
+        }
+        let frac = r - a;
+        if frac < 1e-15 {
+            return None;
+        }
+        r = 1.0 / frac;
+        (p0, q0, p1, q1) = (p1, q1, p, q);
+    }
+    None
+}
+
+// 3π/4、-π/2、√2/2、1/3 这样的写法
+fn format_multiple(p: i64, q: u64, name: &str) -> String {
+    let sign = if p < 0 { "-" } else { "" };
+    let p = p.unsigned_abs();
+    let numerator = match (p, name) {
+        (_, "") => p.to_string(),
+        (1, _) => name.to_string(),
+        _ => format!("{}{}", p, name),
+    };
+    if q == 1 {
+        format!("{}{}", sign, numerator)
+    } else {
+        format!("{}{}/{}", sign, numerator, q)
+    }
+}
+
+// 连分数展开 [a0; a1, a2, ...]，展开到渐近分数和 x 几乎相等为止
+pub fn continued_fraction(x: f64) -> String {
+    let mut terms = Vec::new();
+    let (mut p0, mut q0, mut p1, mut q1) = (0.0, 1.0, 1.0, 0.0);
+    let mut r = x;
+    for _ in 0..MAX_TERMS {
+        let a = r.floor();
+        terms.push(a);
+        let (p, q) = (a * p1 + p0, a * q1 + q0);
+        let frac = r - a;
+        if (p / q - x).abs() <= 1e-12 * x.abs().max(1.0) || frac <= 0.0 {
+            break;
+        }
+        r = 1.0 / frac;
+        (p0, q0, p1, q1) = (p1, q1, p, q);
+    }
+    let tail: Vec<String> = terms[1..].iter().map(|a| a.to_string()).collect();
+    if tail.is_empty() {
+        format!("[{}]", terms[0])
+    } else {
+        format!("[{}; {}]", terms[0], tail.join(", "))
+    }
+}
@@ -0,0 +1,272 @@
+use eframe::egui;
+
+use crate::engine::{evaluate, Context, Value};
+
+// 最多能撤销的步数
+const UNDO_LIMIT: usize = 100;
+// 输入行按表达式求值，限制步数免得卡住界面
+const ENTRY_STEPS: u64 = 100_000;
+// 栈为空时也至少显示这么多层，和 HP 计算器一样
+const MIN_LEVELS: usize = 4;
+
+#[derive(Clone, Copy)]
+enum Command {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+    Neg,
+    Inv,
+    Swap,
+    Roll,
+    Drop,
+    Dup,
+    Over,
+    Clear,
+}
+
+// 运算符按键，键盘输入的运算符也按这张表找
+const OPERATORS: [(&str, char, Command); 5] = [
+    ("+", '+', Command::Add),
+    ("−", '-', Command::Sub),
+    ("×", '*', Command::Mul),
+    ("÷", '/', Command::Div),
+    ("^", '^', Command::Pow),
+];
+
+const COMMANDS: [(&str, &str, Command); 8] = [
+    ("±", "Negate level 1", Command::Neg),
+    ("1/x", "Reciprocal of level 1", Command::Inv),
+    ("swap", "Exchange levels 1 and 2", Command::Swap),
+    (
+        "roll",
+        "Roll down: move level 1 to the top of the stack",
+        Command::Roll,
+    ),
+    ("drop", "Remove level 1", Command::Drop),
+    ("dup", "Copy level 1", Command::Dup),
+    ("over", "Copy level 2 onto the stack", Command::Over),
+    ("clear", "Empty the stack", Command::Clear),
+];
+
+// 逆波兰模式：Enter 把输入压栈，运算符从栈上取操作数，栈的最后一项是第 1 层
+#[derive(Default)]
+pub struct RpnStack {
+    stack: Vec<Value>,
+    entry: String,
+    // 每次操作前的栈，用于撤销
+    undo: Vec<Vec<Value>>,
+    status: Option<String>,
+}
+
+impl RpnStack {
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    // 打开会话时整个替换，撤销记录随之清空
+    pub fn set_stack(&mut self, stack: Vec<Value>) {
+        self.stack = stack;
+        self.undo.clear();
+        self.entry.clear();
+        self.status = None;
+    }
+
+    pub fn top(&self) -> Option<&Value> {
+        self.stack.last()
+    }
+
+    // 从存储调出的数放进输入行
+    pub fn append_entry(&mut self, text: &str) {
+        self.entry.push_str(text);
+    }
+
+    // 输入行不为空时求值后压栈，为空时复制第 1 层
+    fn enter(&mut self, ctx: &Context) {
+        if self.entry.trim().is_empty() {
+            self.run(Command::Dup, ctx);
+            return;
+        }
+        let mut scratch = ctx.clone();
+        scratch.limit_steps(ENTRY_STEPS);
+        match evaluate(&self.entry, &mut scratch) {
+            Ok(value) => {
+                self.save_undo(self.stack.clone());
+                self.stack.push(value);
+                self.entry.clear();
+                self.status = None;
+            }
+            Err(error) => self.status = Some(error.to_string()),
+        }
+    }
+
+    fn save_undo(&mut self, stack: Vec<Value>) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(stack);
+    }
+
+    // 输入行里还有数时先压栈再执行，出错时栈保持原样
+    fn run(&mut self, command: Command, ctx: &Context) {
+        // 压栈和运算合成一步撤销，撤销点就是压栈之前
+        let pushed = !self.entry.trim().is_empty();
+        if pushed {
+            self.enter(ctx);
+            if self.status.is_some() {
+                return;
+            }
+        }
+        let before = self.stack.clone();
+        match apply(&mut self.stack, command) {
+            Ok(()) => {
+                if !pushed {
+                    self.save_undo(before);
+                }
+                self.status = None;
+            }
+            Err(message) => {
+                self.stack = before;
+                self.status = Some(message);
+            }
+        }
+    }
+
+    fn undo(&mut self) {
+        match self.undo.pop() {
+            Some(stack) => {
+                self.stack = stack;
+                self.status = None;
+            }
+            None => self.status = Some("nothing to undo".to_string()),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &Context) {
+        let levels = self.stack.len().max(MIN_LEVELS);
+        egui::Grid::new("rpn_stack").num_columns(2).show(ui, |ui| {
+            for level in (1..=levels).rev() {
+                ui.monospace(format!("{}:", level));
+                let text = self
+                    .stack
+                    .len()
+                    .checked_sub(level)
+                    .map_or(String::new(), |i| self.stack[i].to_string());
+                ui.label(egui::RichText::new(text).monospace().size(18.0));
+                ui.end_row();
+            }
+        });
+
+        let entry_id = egui::Id::new("rpn_entry");
+        // 输入行为空时退格删掉第 1 层
+        let focused = ui.memory(|m| m.has_focus(entry_id));
+        if focused && self.entry.is_empty() && ui.input(|i| i.key_pressed(egui::Key::Backspace)) {
+            self.run(Command::Drop, ctx);
+        }
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.entry)
+                .id(entry_id)
+                .font(egui::FontId::proportional(20.0))
+                .desired_width(f32::INFINITY),
+        );
+        if response.changed() {
+            self.typed_operator(ctx);
+        }
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            self.enter(ctx);
+            response.request_focus();
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Enter").clicked() {
+                self.enter(ctx);
+            }
+            for (label, _, command) in OPERATORS {
+                if ui.button(label).clicked() {
+                    self.run(command, ctx);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            for (label, help, command) in COMMANDS {
+                if ui.button(label).on_hover_text(help).clicked() {
+                    self.run(command, ctx);
+                }
+            }
+            if ui
+                .button("undo")
+                .on_hover_text("Undo the last stack operation")
+                .clicked()
+            {
+                self.undo();
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.colored_label(egui::Color32::RED, status);
+        }
+    }
+
+    // 在数字后面敲运算符时直接运算，比如 "3+" 等于压入 3 再按 +；
+    // 其他情况输入行按表达式处理，1e-5 里的减号也不算；
+    // 输入行是空的时候敲 - 是负号，-5 这样的负数才能输入
+    fn typed_operator(&mut self, ctx: &Context) {
+        let Some(last) = self.entry.chars().last() else {
+            return;
+        };
+        let Some(&(_, _, command)) = OPERATORS.iter().find(|(_, key, _)| *key == last) else {
+            return;
+        };
+        let number = self.entry[..self.entry.len() - 1].trim();
+        if number.is_empty() && last == '-' {
+            return;
+        }
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        let is_number = number.is_empty()
+            || (!digits.ends_with(['e', 'E']) && Value::parse_literal(digits).is_some());
+        if is_number {
+            self.entry = number.to_string();
+            self.run(command, ctx);
+        }
+    }
+}
+
+fn apply(stack: &mut Vec<Value>, command: Command) -> Result<(), String> {
+    let needed = match command {
+        Command::Clear => 0,
+        Command::Neg | Command::Inv | Command::Roll | Command::Drop | Command::Dup => 1,
+        _ => 2,
+    };
+    if stack.len() < needed {
+        return Err(format!(
+            "too few arguments: needs {} level(s), stack has {}",
+            needed,
+            stack.len()
+        ));
+    }
+    let binary = |stack: &mut Vec<Value>, op: fn(&Value, &Value) -> Result<Value, String>| {
+        let y = stack.pop().unwrap_or(Value::Int(0.into()));
+        let x = stack.pop().unwrap_or(Value::Int(0.into()));
+        stack.push(op(&x, &y)?);
+        Ok(())
+    };
+    let n = stack.len();
+    match command {
+        Command::Add => return binary(stack, Value::add),
+        Command::Sub => return binary(stack, Value::sub),
+        Command::Mul => return binary(stack, Value::mul),
+        Command::Div => return binary(stack, Value::div),
+        Command::Pow => return binary(stack, Value::pow),
+        Command::Neg => stack[n - 1] = stack[n - 1].neg()?,
+        Command::Inv => stack[n - 1] = Value::Int(1.into()).div(&stack[n - 1])?,
+        Command::Swap => stack.swap(n - 1, n - 2),
+        Command::Roll => stack.rotate_right(1),
+        Command::Drop => {
+            stack.pop();
+        }
+        Command::Dup => stack.push(stack[n - 1].clone()),
+        Command::Over => stack.push(stack[n - 2].clone()),
+        Command::Clear => stack.clear(),
+    }
+    Ok(())
+}
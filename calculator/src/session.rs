@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use num::complex::Complex64;
+use num::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{evaluate, Context, Value};
+use crate::functions::Polynomial;
+
+// 会话文件的格式版本，格式变化时加一并在 MIGRATIONS 里补上升级函数
+pub const VERSION: u32 = 2;
+
+// 第 i 项把第 i + 1 版的文件升级到第 i + 2 版，读文件时依次执行
+const MIGRATIONS: &[fn(&mut serde_json::Value)] = &[add_rpn];
+
+// 第 2 版加了逆波兰模式和它的栈
+fn add_rpn(json: &mut serde_json::Value) {
+    json["settings"]["rpn_mode"] = false.into();
+    json["rpn"] = serde_json::Value::Array(Vec::new());
+}
+
+// 保存到文件的整个会话：设置、变量、用户函数、历史和工作表，JSON 格式
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub settings: Settings,
+    pub variables: BTreeMap<String, SavedValue>,
+    // 每项是一行 f(x) = ... 定义
+    pub functions: Vec<String>,
+    pub history: Vec<SavedEntry>,
+    // 工作表每个单元格的源码
+    pub worksheet: Vec<String>,
+    // 逆波兰栈，从底到顶
+    pub rpn: Vec<SavedValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Settings {
+    pub implicit_multiplication: bool,
+    pub rpn_mode: bool,
+    // 只记种子，打开后随机数从种子的开头重新产生
+    pub seed: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntry {
+    pub input: String,
+    pub value: SavedValue,
+}
+
+// 数字都存成文本，大整数和 inf、NaN 才能原样读回来
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedValue {
+    Int(String),
+    Float(String),
+    Factors(Vec<(String, u32)>),
+    List(Vec<SavedValue>),
+    Complex(String, String),
+    // 系数按升幂排列
+    Poly { var: String, coeffs: Vec<String> },
+    Estimate(String, String),
+    Bool(bool),
+    Text(String),
+}
+
+// 1e300 这样的短写法，读回来完全一样
+fn real(x: f64) -> String {
+    format!("{:?}", x)
+}
+
+impl From<&Value> for SavedValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Int(n) => SavedValue::Int(n.to_string()),
+            Value::Float(x) => SavedValue::Float(real(*x)),
+            Value::Factors(factors) => {
+                SavedValue::Factors(factors.iter().map(|(p, k)| (p.to_string(), *k)).collect())
+            }
+            Value::List(items) => SavedValue::List(items.iter().map(SavedValue::from).collect()),
+            Value::Complex(z) => SavedValue::Complex(real(z.re), real(z.im)),
+            Value::Poly(p) => SavedValue::Poly {
+                var: p.var().to_string(),
+                coeffs: p.coeffs().iter().copied().map(real).collect(),
+            },
+            Value::Estimate(x, err) => SavedValue::Estimate(real(*x), real(*err)),
+            Value::Bool(b) => SavedValue::Bool(*b),
+            Value::Text(text) => SavedValue::Text(text.clone()),
+        }
+    }
+}
+
+impl SavedValue {
+    pub fn to_value(&self) -> Result<Value, String> {
+        let float = |text: &str| {
+            text.parse::<f64>()
+                .map_err(|_| format!("invalid number '{}'", text))
+        };
+        let int = |text: &str| {
+            text.parse::<BigInt>()
+                .map_err(|_| format!("invalid integer '{}'", text))
+        };
+        Ok(match self {
+            SavedValue::Int(n) => Value::Int(int(n)?),
+            SavedValue::Float(x) => Value::Float(float(x)?),
+            SavedValue::Factors(factors) => Value::Factors(
+                factors
+                    .iter()
+                    .map(|(p, k)| Ok((int(p)?, *k)))
+                    .collect::<Result<_, String>>()?,
+            ),
+            SavedValue::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(SavedValue::to_value)
+                    .collect::<Result<_, _>>()?,
+            ),
+            SavedValue::Complex(re, im) => Value::Complex(Complex64::new(float(re)?, float(im)?)),
+            SavedValue::Poly { var, coeffs } => Value::Poly(Polynomial::new(
+                coeffs.iter().map(|c| float(c)).collect::<Result<_, _>>()?,
+                var,
+            )),
+            SavedValue::Estimate(x, err) => Value::Estimate(float(x)?, float(err)?),
+            SavedValue::Bool(b) => Value::Bool(*b),
+            SavedValue::Text(text) => Value::Text(text.clone()),
+        })
+    }
+}
+
+impl Session {
+    // 内置常量没有改动过的不保存
+    pub fn capture<'a>(
+        context: &Context,
+        history: impl Iterator<Item = (&'a str, &'a Value)>,
+        worksheet: &str,
+        rpn_mode: bool,
+        rpn: &[Value],
+    ) -> Session {
+        let defaults = Context::default();
+        let builtin: BTreeMap<&str, &Value> = defaults.variables().collect();
+        Session {
+            version: VERSION,
+            settings: Settings {
+                implicit_multiplication: context.implicit_multiplication(),
+                rpn_mode,
+                seed: context.seed(),
+            },
+            variables: context
+                .variables()
+                .filter(|(name, value)| builtin.get(name) != Some(value))
+                .map(|(name, value)| (name.to_string(), SavedValue::from(value)))
+                .collect(),
+            functions: context.function_definitions(),
+            history: history
+                .map(|(input, value)| SavedEntry {
+                    input: input.to_string(),
+                    value: SavedValue::from(value),
+                })
+                .collect(),
+            worksheet: worksheet.lines().map(str::to_string).collect(),
+            rpn: rpn.iter().map(SavedValue::from).collect(),
+        }
+    }
+
+    // 先按版本号依次升级，再按当前格式读取
+    pub fn from_json(text: &str) -> Result<Session, String> {
+        let mut json: serde_json::Value =
+            serde_json::from_str(text).map_err(|err| format!("invalid session file: {}", err))?;
+        let version = json
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or("not a calculator session: missing 'version'")?;
+        if version == 0 || version > VERSION as u64 {
+            return Err(format!(
+                "unsupported session version {} (this build reads up to {})",
+                version, VERSION
+            ));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(&mut json);
+        }
+        json["version"] = VERSION.into();
+        serde_json::from_value(json).map_err(|err| format!("invalid session file: {}", err))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    // 重建求值上下文：设置、变量和函数，函数定义按源码重新解析
+    pub fn context(&self) -> Result<Context, String> {
+        let mut context = Context::default();
+        context.set_implicit_multiplication(self.settings.implicit_multiplication);
+        context.set_seed(self.settings.seed);
+        for (name, value) in &self.variables {
+            context.set_variable(name, value.to_value()?);
+        }
+        for source in &self.functions {
+            evaluate(source, &mut context).map_err(|err| format!("{}: {}", source, err))?;
+        }
+        Ok(context)
+    }
+
+    pub fn rpn(&self) -> Result<Vec<Value>, String> {
+        self.rpn.iter().map(SavedValue::to_value).collect()
+    }
+
+    pub fn history(&self) -> Result<Vec<(String, Value)>, String> {
+        self.history
+            .iter()
+            .map(|entry| Ok((entry.input.clone(), entry.value.to_value()?)))
+            .collect()
+    }
+}
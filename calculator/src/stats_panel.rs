@@ -0,0 +1,117 @@
+use eframe::egui;
+use rand::Rng;
+
+use crate::engine::Context;
+use crate::functions::Distribution;
+
+// "Stats" 面板：选分布、调参数，查看 pdf/cdf/分位数，并管理随机数种子
+pub struct StatsPanel {
+    distribution: Distribution,
+    x: f64,
+    p: f64,
+    seed: u64,
+    last_random: Option<f64>,
+}
+
+impl Default for StatsPanel {
+    fn default() -> Self {
+        StatsPanel {
+            distribution: Distribution::ALL[0],
+            x: 0.0,
+            p: 0.5,
+            seed: 0,
+            last_random: None,
+        }
+    }
+}
+
+impl StatsPanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &mut Context) {
+        egui::ComboBox::from_label("Distribution")
+            .selected_text(self.distribution.name())
+            .show_ui(ui, |ui| {
+                for dist in Distribution::ALL {
+                    let selected =
+                        std::mem::discriminant(&dist) == std::mem::discriminant(&self.distribution);
+                    if ui.selectable_label(selected, dist.name()).clicked() && !selected {
+                        self.distribution = dist;
+                    }
+                }
+            });
+
+        ui.horizontal(|ui| match &mut self.distribution {
+            Distribution::Normal { mean, sd } => {
+                ui.add(egui::DragValue::new(mean).speed(0.1).prefix("μ = "));
+                ui.add(egui::DragValue::new(sd).speed(0.1).prefix("σ = "));
+            }
+            Distribution::Binomial { n, p } => {
+                ui.add(egui::DragValue::new(n).prefix("n = "));
+                ui.add(
+                    egui::DragValue::new(p)
+                        .speed(0.01)
+                        .range(0.0..=1.0)
+                        .prefix("p = "),
+                );
+            }
+            Distribution::Poisson { lambda } => {
+                ui.add(egui::DragValue::new(lambda).speed(0.1).prefix("λ = "));
+            }
+            Distribution::Uniform { a, b } => {
+                ui.add(egui::DragValue::new(a).speed(0.1).prefix("a = "));
+                ui.add(egui::DragValue::new(b).speed(0.1).prefix("b = "));
+            }
+            Distribution::Exponential { rate } => {
+                ui.add(egui::DragValue::new(rate).speed(0.1).prefix("λ = "));
+            }
+            Distribution::StudentT { df } | Distribution::ChiSquared { df } => {
+                ui.add(egui::DragValue::new(df).speed(0.1).prefix("ν = "));
+            }
+        });
+
+        match self.distribution.validate() {
+            Ok(()) => {
+                let dist = self.distribution;
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.x).speed(0.1).prefix("x = "));
+                    let density = if dist.is_discrete() {
+                        "P(X = x)"
+                    } else {
+                        "pdf(x)"
+                    };
+                    ui.label(format!("{} = {:.6}", density, dist.pdf(self.x)));
+                    ui.label(format!("cdf(x) = {:.6}", dist.cdf(self.x)));
+                });
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut self.p)
+                            .speed(0.01)
+                            .range(0.0..=1.0)
+                            .prefix("p = "),
+                    );
+                    ui.label(format!("quantile(p) = {:.6}", dist.quantile(self.p)));
+                });
+            }
+            Err(message) => {
+                ui.colored_label(egui::Color32::RED, message);
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.seed).prefix("seed = "));
+            if ui.button("Set seed").clicked() {
+                ctx.set_seed(self.seed);
+                self.last_random = None;
+            }
+            ui.label(format!("current seed: {}", ctx.seed()));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("rand()").clicked() {
+                self.last_random = Some(ctx.rng().gen());
+            }
+            if let Some(value) = self.last_random {
+                ui.label(format!("{}", value));
+            }
+        });
+    }
+}
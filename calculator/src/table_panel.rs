@@ -0,0 +1,366 @@
+use eframe::egui;
+
+use crate::engine::{self, evaluate, Context, Statement, Value};
+
+// 最多生成的行数
+const MAX_ROWS: usize = 10_000;
+// 每个格子最多求值的节点数
+const CELL_STEPS: u64 = 100_000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Range,
+    List,
+}
+
+// "Table" 面板：一列 x 加上若干列关于 x 的表达式的值，可以复制、导出成 CSV / Markdown
+pub struct TablePanel {
+    columns: Vec<String>,
+    source: Source,
+    start: String,
+    stop: String,
+    step: String,
+    list: String,
+    headers: Vec<String>,
+    xs: Vec<Value>,
+    // 按行存，每行一个格子对应一列表达式
+    rows: Vec<Vec<Result<Value, String>>>,
+    status: Option<String>,
+}
+
+impl Default for TablePanel {
+    fn default() -> Self {
+        TablePanel {
+            columns: vec!["x^2".to_string()],
+            source: Source::Range,
+            start: "0".to_string(),
+            stop: "1".to_string(),
+            step: "0.1".to_string(),
+            list: "1, 2, 5, 10".to_string(),
+            headers: Vec::new(),
+            xs: Vec::new(),
+            rows: Vec::new(),
+            status: None,
+        }
+    }
+}
+
+impl TablePanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &Context) {
+        let mut removed = None;
+        for (i, column) in self.columns.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("y{} =", i + 1));
+                ui.add(
+                    egui::TextEdit::singleline(column)
+                        .code_editor()
+                        .hint_text("expression in x")
+                        .desired_width(200.0),
+                );
+                if ui
+                    .small_button("✖")
+                    .on_hover_text("Remove column")
+                    .clicked()
+                {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(i) = removed {
+            self.columns.remove(i);
+        }
+        if ui.button("Add column").clicked() {
+            self.columns.push(String::new());
+        }
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.source, Source::Range, "range");
+            ui.radio_value(&mut self.source, Source::List, "list of x");
+        });
+        ui.horizontal(|ui| match self.source {
+            Source::Range => {
+                for (label, text) in [
+                    ("from", &mut self.start),
+                    ("to", &mut self.stop),
+                    ("step", &mut self.step),
+                ] {
+                    ui.label(label);
+                    ui.add(egui::TextEdit::singleline(text).desired_width(60.0));
+                }
+            }
+            Source::List => {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.list)
+                        .hint_text("1, 2, 3 or a list variable")
+                        .desired_width(240.0),
+                );
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Generate").clicked() {
+                self.generate(ctx);
+            }
+            if !self.rows.is_empty() {
+                if ui.button("Copy CSV").clicked() {
+                    ui.ctx().copy_text(self.to_csv());
+                    self.status = Some("copied as CSV".to_string());
+                }
+                if ui.button("Copy Markdown").clicked() {
+                    ui.ctx().copy_text(self.to_markdown());
+                    self.status = Some("copied as Markdown".to_string());
+                }
+                if ui.button("Export…").clicked() {
+                    self.export();
+                }
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        if self.rows.is_empty() {
+            return;
+        }
+
+        // 最多上万行，只排版看得见的行；第 0 行是表头
+        let row_height = ui.spacing().interact_size.y;
+        egui::ScrollArea::both().max_height(240.0).show_rows(
+            ui,
+            row_height,
+            self.rows.len() + 1,
+            |ui, visible| {
+                egui::Grid::new("value_table")
+                    .striped(true)
+                    .min_row_height(row_height)
+                    .num_columns(self.headers.len())
+                    .start_row(visible.start)
+                    .show(ui, |ui| {
+                        for i in visible {
+                            let Some(i) = i.checked_sub(1) else {
+                                for title in &self.headers {
+                                    ui.strong(title);
+                                }
+                                ui.end_row();
+                                continue;
+                            };
+                            ui.monospace(self.xs[i].to_string());
+                            for cell in &self.rows[i] {
+                                match cell {
+                                    Ok(value) => {
+                                        ui.monospace(value.to_string());
+                                    }
+                                    Err(message) => {
+                                        ui.colored_label(egui::Color32::RED, "⚠")
+                                            .on_hover_text(message);
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            },
+        );
+    }
+
+    fn generate(&mut self, ctx: &Context) {
+        self.headers.clear();
+        self.xs.clear();
+        self.rows.clear();
+        let xs = match self.source {
+            Source::Range => self.range(ctx),
+            Source::List => self.list_values(ctx),
+        };
+        let xs = match xs {
+            Ok(xs) if xs.len() > MAX_ROWS => {
+                self.status = Some(format!("too many rows (at most {})", MAX_ROWS));
+                return;
+            }
+            Ok(xs) => xs,
+            Err(message) => {
+                self.status = Some(message);
+                return;
+            }
+        };
+
+        let sources: Vec<&str> = self
+            .columns
+            .iter()
+            .map(|c| c.trim())
+            .filter(|c| !c.is_empty())
+            .collect();
+        if sources.is_empty() {
+            self.status = Some("enter an expression in x".to_string());
+            return;
+        }
+        let columns: Vec<_> = sources
+            .iter()
+            .map(|source| column_values(source, &xs, ctx))
+            .collect();
+        self.headers = std::iter::once("x")
+            .chain(sources)
+            .map(str::to_string)
+            .collect();
+        self.rows = (0..xs.len())
+            .map(|i| columns.iter().map(|column| column[i].clone()).collect())
+            .collect();
+        self.status = Some(format!("{} rows", xs.len()));
+        self.xs = xs;
+    }
+
+    // 起止和步长都可以是表达式；都是整数时 x 也是整数，结果保持精确
+    fn range(&self, ctx: &Context) -> Result<Vec<Value>, String> {
+        let mut scratch = ctx.clone();
+        // 和单元格一样限制步数，写错的起止表达式不会卡住界面
+        let mut bound = |label: &str, text: &str| {
+            scratch.limit_steps(CELL_STEPS);
+            evaluate(text, &mut scratch).map_err(|e| format!("{}: {}", label, e))
+        };
+        let start = bound("from", &self.start)?;
+        let stop = bound("to", &self.stop)?;
+        let step = bound("step", &self.step)?;
+        let (a, b, h) = (start.to_f64(), stop.to_f64(), step.to_f64());
+        if !(a.is_finite() && b.is_finite() && h.is_finite()) {
+            return Err("range bounds and step must be real numbers".to_string());
+        }
+        if h == 0.0 || (b - a) / h < 0.0 {
+            return Err("step does not move from start towards stop".to_string());
+        }
+        // 容忍 0.1 这种步长累计的舍入误差，终点能取到
+        let steps = ((b - a) / h + 1e-9).floor();
+        if steps >= MAX_ROWS as f64 {
+            return Err(format!("too many rows (at most {})", MAX_ROWS));
+        }
+        let exact = matches!((&start, &step), (Value::Int(_), Value::Int(_)));
+        (0..=steps as usize)
+            .map(|i| {
+                if exact {
+                    step.mul(&Value::Int(i.into()))?.add(&start)
+                } else {
+                    Ok(Value::Float(tidy(a + i as f64 * h)))
+                }
+            })
+            .collect()
+    }
+
+    // "1, 2, 3" 直接写数，也可以是结果为列表的表达式，比如列表变量
+    fn list_values(&self, ctx: &Context) -> Result<Vec<Value>, String> {
+        let mut scratch = ctx.clone();
+        scratch.limit_steps(CELL_STEPS);
+        if let Ok(Value::List(items)) = evaluate(&self.list, &mut scratch) {
+            return Ok(items);
+        }
+        scratch.limit_steps(CELL_STEPS);
+        match evaluate(&format!("[{}]", self.list), &mut scratch) {
+            Ok(Value::List(items)) if !items.is_empty() => Ok(items),
+            Ok(_) => Err("list of x is empty".to_string()),
+            Err(err) => Err(format!("list of x: {}", err)),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        let header: Vec<String> = self.headers.iter().map(|h| csv_field(h)).collect();
+        csv.push_str(&header.join(","));
+        csv.push('\n');
+        for (x, row) in self.xs.iter().zip(&self.rows) {
+            let fields: Vec<String> = std::iter::once(x.to_string())
+                .chain(row.iter().map(cell_text))
+                .map(|field| csv_field(&field))
+                .collect();
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn to_markdown(&self) -> String {
+        let line = |fields: Vec<String>| {
+            let escaped: Vec<String> = fields.iter().map(|f| f.replace('|', "\\|")).collect();
+            format!("| {} |\n", escaped.join(" | "))
+        };
+        let mut markdown = line(self.headers.clone());
+        markdown.push_str(&line(vec!["---".to_string(); self.headers.len()]));
+        for (x, row) in self.xs.iter().zip(&self.rows) {
+            markdown.push_str(&line(
+                std::iter::once(x.to_string())
+                    .chain(row.iter().map(cell_text))
+                    .collect(),
+            ));
+        }
+        markdown
+    }
+
+    // 按选的扩展名决定格式
+    fn export(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("Markdown", &["md"])
+            .set_file_name("table.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let markdown = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("md"));
+        let text = if markdown {
+            self.to_markdown()
+        } else {
+            self.to_csv()
+        };
+        self.status = Some(match std::fs::write(&path, text) {
+            Ok(()) => format!("saved to {}", path.display()),
+            Err(err) => format!("export failed: {}", err),
+        });
+    }
+}
+
+// 一列表达式在所有 x 上的值。x 是小数时用编译好的字节码；
+// 第一个小数 x 按语法树求出的不是小数（布尔值、分解式之类）时整列都按语法树求值，显示才一致
+fn column_values(source: &str, xs: &[Value], ctx: &Context) -> Vec<Result<Value, String>> {
+    let expr = match engine::parse(source, ctx) {
+        Ok(Statement::Expr(expr)) => expr,
+        Ok(_) => return vec![Err("not an expression".to_string()); xs.len()],
+        Err(err) => return vec![Err(err.to_string()); xs.len()],
+    };
+    let program = engine::compile(&expr, ctx, &["x"]).ok();
+    let mut fast = None;
+    let mut scratch = ctx.clone();
+    xs.iter()
+        .map(|x| {
+            if let (Value::Float(v), Some(program), Some(true)) = (x, &program, fast) {
+                let y = program.run(&[*v]);
+                if !y.is_nan() {
+                    return Ok(Value::Float(y));
+                }
+            }
+            scratch.limit_steps(CELL_STEPS);
+            let value = engine::evaluate_at(&expr, &mut scratch, "x", x.clone());
+            if matches!(x, Value::Float(_)) && value.is_ok() && fast.is_none() {
+                fast = Some(matches!(value, Ok(Value::Float(_))));
+            }
+            value.map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+// 保留 12 位有效数字，0.1 步长算出的 0.30000000000000004 取成 0.3
+fn tidy(x: f64) -> f64 {
+    format!("{:.11e}", x).parse().unwrap_or(x)
+}
+
+fn cell_text(cell: &Result<Value, String>) -> String {
+    match cell {
+        Ok(value) => value.to_string(),
+        Err(message) => format!("error: {}", message),
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,256 @@
+use eframe::egui;
+
+use crate::engine::{evaluate, Context, Value};
+
+// 每行金额最多求值的节点数
+const LINE_STEPS: u64 = 100_000;
+// 导出报表里数字列的宽度
+const COLUMN: usize = 18;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    const ALL: [Op; 4] = [Op::Add, Op::Sub, Op::Mul, Op::Div];
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "−",
+            Op::Mul => "×",
+            Op::Div => "÷",
+        }
+    }
+
+    fn apply(self, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+        match self {
+            Op::Add => lhs.add(rhs),
+            Op::Sub => lhs.sub(rhs),
+            Op::Mul => lhs.mul(rhs),
+            Op::Div => lhs.div(rhs),
+        }
+    }
+}
+
+// 纸带上的一行：运算符和金额，金额可以是表达式
+struct Line {
+    op: Op,
+    amount: String,
+    // 算到这一行为止的小计
+    subtotal: Result<Value, String>,
+}
+
+// 加法机式的纸带：每个数和运算符各占一行，带小计，改动某行后重算它下面的所有行
+pub struct Tape {
+    lines: Vec<Line>,
+    entry: String,
+    decimals: usize,
+    status: Option<String>,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Tape {
+            lines: Vec::new(),
+            entry: String::new(),
+            decimals: 2,
+            status: None,
+        }
+    }
+}
+
+impl Tape {
+    // 从第 k 行开始重算小计，出错的行之后都不再有小计
+    fn recompute_from(&mut self, k: usize, ctx: &Context) {
+        let mut subtotal = match k.checked_sub(1) {
+            Some(i) => self.lines[i].subtotal.clone(),
+            None => Ok(Value::Int(0.into())),
+        };
+        for line in &mut self.lines[k..] {
+            subtotal = subtotal
+                .map_err(|_| "error above".to_string())
+                .and_then(|total| {
+                    let mut scratch = ctx.clone();
+                    scratch.limit_steps(LINE_STEPS);
+                    let amount = evaluate(&line.amount, &mut scratch).map_err(|e| e.to_string())?;
+                    line.op.apply(&total, &amount)
+                });
+            line.subtotal = subtotal.clone();
+        }
+    }
+
+    fn push(&mut self, op: Op, ctx: &Context) {
+        if self.entry.trim().is_empty() {
+            return;
+        }
+        self.lines.push(Line {
+            op,
+            amount: std::mem::take(&mut self.entry),
+            subtotal: Ok(Value::Int(0.into())),
+        });
+        self.recompute_from(self.lines.len() - 1, ctx);
+    }
+
+    fn total(&self) -> Option<&Result<Value, String>> {
+        self.lines.last().map(|line| &line.subtotal)
+    }
+
+    fn show_subtotal(&self, subtotal: &Result<Value, String>) -> String {
+        match subtotal {
+            Ok(value) => fixed(value, self.decimals),
+            Err(message) => format!("error: {}", message),
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &Context) {
+        let mut edited = None;
+        let mut removed = None;
+        egui::Grid::new("tape")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, line) in self.lines.iter_mut().enumerate() {
+                    egui::ComboBox::from_id_salt(("tape_op", i))
+                        .width(30.0)
+                        .selected_text(line.op.symbol())
+                        .show_ui(ui, |ui| {
+                            for op in Op::ALL {
+                                if ui.selectable_value(&mut line.op, op, op.symbol()).changed() {
+                                    edited = Some(i);
+                                }
+                            }
+                        });
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut line.amount)
+                            .horizontal_align(egui::Align::Max)
+                            .desired_width(120.0),
+                    );
+                    if response.changed() {
+                        edited = Some(i);
+                    }
+                    match &line.subtotal {
+                        Ok(value) => {
+                            ui.monospace(format!(
+                                "{:>w$}",
+                                fixed(value, self.decimals),
+                                w = COLUMN
+                            ));
+                        }
+                        Err(message) => {
+                            ui.colored_label(egui::Color32::RED, message);
+                        }
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove line").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = removed {
+            self.lines.remove(i);
+            if i < self.lines.len() {
+                self.recompute_from(i, ctx);
+            }
+        } else if let Some(i) = edited {
+            self.recompute_from(i, ctx);
+        }
+
+        // Enter 相当于按 +，和加法机一样
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.entry)
+                .hint_text("amount")
+                .horizontal_align(egui::Align::Max)
+                .desired_width(160.0),
+        );
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            self.push(Op::Add, ctx);
+            response.request_focus();
+        }
+        ui.horizontal(|ui| {
+            for op in Op::ALL {
+                if ui.button(op.symbol()).clicked() {
+                    self.push(op, ctx);
+                }
+            }
+            ui.separator();
+            ui.label("Decimals");
+            ui.add(egui::DragValue::new(&mut self.decimals).range(0..=10));
+        });
+        if let Some(total) = self.total() {
+            ui.strong(format!("Total: {}", self.show_subtotal(total)));
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Clear tape").clicked() {
+                self.lines.clear();
+                self.status = None;
+            }
+            if ui.button("Export…").clicked() {
+                self.export();
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+    }
+
+    // 纯文本报表：每行序号、运算符、金额和小计，最后是合计
+    pub fn report(&self) -> String {
+        let width = 6 + 2 * COLUMN + 2;
+        let mut text = String::from("Calculator tape\n");
+        text.push_str(&"=".repeat(width));
+        text.push('\n');
+        for (i, line) in self.lines.iter().enumerate() {
+            text.push_str(&format!(
+                "{:>4} {} {:>w$} {:>w$}\n",
+                i + 1,
+                line.op.symbol(),
+                line.amount.trim(),
+                self.show_subtotal(&line.subtotal),
+                w = COLUMN
+            ));
+        }
+        text.push_str(&"-".repeat(width));
+        text.push('\n');
+        let total = match self.total() {
+            Some(total) => self.show_subtotal(total),
+            None => fixed(&Value::Int(0.into()), self.decimals),
+        };
+        text.push_str(&format!(
+            "{:<w$}{:>t$}\n",
+            format!("Total ({} lines)", self.lines.len()),
+            total,
+            w = width - COLUMN,
+            t = COLUMN
+        ));
+        text
+    }
+
+    fn export(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Text", &["txt"])
+            .set_file_name("tape.txt")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(match std::fs::write(&path, self.report()) {
+            Ok(()) => format!("exported to {}", path.display()),
+            Err(err) => format!("export failed: {}", err),
+        });
+    }
+}
+
+// 实数按固定小数位显示，其他值照常显示
+fn fixed(value: &Value, decimals: usize) -> String {
+    match value {
+        Value::Int(_) | Value::Float(_) | Value::Factors(_) | Value::Estimate(..) => {
+            format!("{:.*}", decimals, value.to_f64())
+        }
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use eframe::egui;
+
+use crate::engine::{dependencies, evaluate, scan, CalcError, Context, Dependencies, Token, Value};
+
+// 每个单元格最多求值的节点数，避免编辑时界面卡住
+const CELL_STEPS: u64 = 100_000;
+
+#[derive(Default)]
+struct Cell {
+    source: String,
+    deps: Option<Dependencies>,
+    result: Option<Result<Value, CalcError>>,
+}
+
+impl Cell {
+    fn new(source: String) -> Self {
+        Cell {
+            source,
+            ..Default::default()
+        }
+    }
+}
+
+// 工作表：按顺序求值的一列单元格，第 n 个单元格的结果可以用 $n 引用，
+// 赋值的变量和定义的函数在后面的单元格里可以直接用
+#[derive(Default)]
+pub struct Worksheet {
+    cells: Vec<Cell>,
+    status: Option<String>,
+}
+
+impl Worksheet {
+    pub fn ui(&mut self, ui: &mut egui::Ui, ctx: &Context) {
+        let mut edited = None;
+        let mut removed = None;
+        egui::Grid::new("worksheet").num_columns(4).show(ui, |ui| {
+            for (i, cell) in self.cells.iter_mut().enumerate() {
+                ui.monospace(format!("${}", i + 1));
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut cell.source)
+                        .code_editor()
+                        .desired_width(240.0),
+                );
+                if response.changed() {
+                    edited = Some(i);
+                }
+                match &cell.result {
+                    Some(Ok(value)) => {
+                        ui.label(format!("= {}", value));
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(egui::Color32::RED, error.to_string());
+                    }
+                    None => {
+                        ui.label("");
+                    }
+                }
+                if ui.small_button("✖").on_hover_text("Remove cell").clicked() {
+                    removed = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Add cell").clicked() {
+                self.cells.push(Cell::default());
+            }
+            if ui.button("Recalculate").clicked() {
+                self.recalculate_all(ctx);
+            }
+            if ui.button("Open…").clicked() {
+                self.open(ctx);
+            }
+            if ui.button("Save…").clicked() {
+                self.save();
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+
+        if let Some(i) = removed {
+            self.remove(i, ctx);
+        } else if let Some(i) = edited {
+            self.edited(i, ctx);
+        }
+    }
+
+    // 被别的单元格引用时不能删除；删除后把后面单元格的编号往前挪，引用跟着改写，再全部重算
+    fn remove(&mut self, i: usize, ctx: &Context) {
+        let user = self.cells.iter().position(|cell| {
+            cell_references(&cell.source)
+                .iter()
+                .any(|(_, n)| *n == i + 1)
+        });
+        if let Some(j) = user {
+            self.status = Some(format!("${} is used by ${}", i + 1, j + 1));
+            return;
+        }
+        self.cells.remove(i);
+        for cell in &mut self.cells {
+            let mut source = cell.source.clone();
+            for (span, n) in cell_references(&cell.source).into_iter().rev() {
+                if n > i + 1 {
+                    source.replace_range(span, &format!("${}", n - 1));
+                }
+            }
+            cell.source = source;
+        }
+        self.status = None;
+        self.recalculate_all(ctx);
+    }
+
+    // 单元格 k 改动后，只重算直接或间接依赖它的单元格
+    fn edited(&mut self, k: usize, ctx: &Context) {
+        let mut changed: BTreeSet<String> = BTreeSet::new();
+        if let Some(old) = self.cells[k].deps.as_ref().and_then(|d| d.defines.clone()) {
+            changed.insert(old);
+        }
+        self.analyze(k, ctx);
+        let mut dirty = vec![false; self.cells.len()];
+        dirty[k] = true;
+        changed.extend(self.outputs(k));
+        for (j, flag) in dirty.iter_mut().enumerate().skip(k + 1) {
+            let Some(deps) = &self.cells[j].deps else {
+                continue;
+            };
+            if !deps.uses.is_disjoint(&changed) {
+                *flag = true;
+                changed.extend(self.outputs(j));
+            }
+        }
+        self.evaluate(&dirty, ctx);
+    }
+
+    fn recalculate_all(&mut self, ctx: &Context) {
+        for i in 0..self.cells.len() {
+            self.analyze(i, ctx);
+        }
+        self.evaluate(&vec![true; self.cells.len()], ctx);
+    }
+
+    fn analyze(&mut self, i: usize, ctx: &Context) {
+        let cell = &mut self.cells[i];
+        cell.deps = dependencies(&cell.source, ctx).ok();
+    }
+
+    // 单元格 i 会影响到的名字：它的编号和它定义的名字
+    fn outputs(&self, i: usize) -> Vec<String> {
+        let mut names = vec![format!("${}", i + 1)];
+        if let Some(name) = self.cells[i].deps.as_ref().and_then(|d| d.defines.clone()) {
+            names.push(name);
+        }
+        names
+    }
+
+    // 从会话上下文的副本开始按顺序走一遍：需要重算的单元格重新求值，
+    // 其余的单元格只把上次的结果放回上下文
+    fn evaluate(&mut self, dirty: &[bool], ctx: &Context) {
+        let mut scope = ctx.clone();
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            if cell.source.trim().is_empty() {
+                cell.result = None;
+                continue;
+            }
+            // 函数定义只是记下函数体，每次都重新执行
+            let function = cell.deps.as_ref().is_some_and(|d| d.function);
+            if dirty[i] || function || cell.result.is_none() {
+                scope.limit_steps(CELL_STEPS);
+                cell.result = Some(evaluate(&cell.source, &mut scope));
+            } else if let (Some(Ok(value)), Some(name)) = (
+                &cell.result,
+                cell.deps.as_ref().and_then(|d| d.defines.as_ref()),
+            ) {
+                scope.set_variable(name, value.clone());
+            }
+            if let Some(Ok(value)) = &cell.result {
+                scope.set_variable(&format!("${}", i + 1), value.clone());
+            }
+        }
+    }
+
+    // .calc 文件就是纯文本，每行一个单元格
+    fn open(&mut self, ctx: &Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Worksheet", &["calc"])
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                self.load(&text, ctx);
+                self.status = Some(format!("opened {}", path.display()));
+            }
+            Err(err) => self.status = Some(format!("open failed: {}", err)),
+        }
+    }
+
+    pub fn load(&mut self, text: &str, ctx: &Context) {
+        self.cells = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Cell::new(line.to_string()))
+            .collect();
+        self.recalculate_all(ctx);
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for cell in &self.cells {
+            text.push_str(&cell.source);
+            text.push('\n');
+        }
+        text
+    }
+
+    fn save(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Worksheet", &["calc"])
+            .set_file_name("worksheet.calc")
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(match std::fs::write(&path, self.to_text()) {
+            Ok(()) => format!("saved to {}", path.display()),
+            Err(err) => format!("save failed: {}", err),
+        });
+    }
+}
+
+// 一行输入里的单元格引用：在原文里的位置和编号
+fn cell_references(source: &str) -> Vec<(Range<usize>, usize)> {
+    let (lexed, _) = scan(source);
+    lexed
+        .tokens
+        .iter()
+        .zip(lexed.spans)
+        .filter_map(|(token, span)| match token {
+            Token::Ident(name) => Some((span, name.strip_prefix('$')?.parse().ok()?)),
+            _ => None,
+        })
+        .collect()
+}